@@ -1,5 +1,6 @@
-use crate::ray::Ray;
-use crate::vec3::{Color, dot, unit_vector, Point3};
+use engine::background::Background;
+use engine::ray::Ray;
+use engine::vec3::{Color, dot, Point3};
 
 // https://raytracing.github.io/books/RayTracingInOneWeekend.html#addingasphere
 // The formula for the radius of sphere at the origin of a 3D space is x^2 + y^2 + z^2 = r^2.
@@ -47,12 +48,10 @@ fn hit_sphere(center: Point3, radius: f64, r: &Ray) -> bool {
     discriminant >= 0.0
 }
 
-pub(crate) fn ray_color(r: &Ray) -> Color {
+pub fn ray_color(r: &Ray) -> Color {
     if hit_sphere(Point3::new(0.0, 0.0, -1.0), 0.5, r) {
         return Color::new(1.0, 0.0, 0.0);
     }
 
-    let unit_direction = unit_vector(r.direction);
-    let a = 0.5 * (unit_direction.y + 1.0);
-    (1.0 - a) * Color::new(1.0, 1.0, 1.0) + a * Color::new(0.6, 0.7, 1.0)
+    Background::sky().value(r)
 }
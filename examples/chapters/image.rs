@@ -1,10 +1,9 @@
-use std::{fs, io};
+use std::io;
 use std::io::Write;
-use crate::vec3::Color;
-
-pub(crate) fn create() {
-    let mut contents = String::with_capacity(1_000_000);
+use engine::output::Framebuffer;
+use engine::vec3::Color;
 
+pub fn create() {
     // Image
 
     let image_width = 256;
@@ -12,9 +11,7 @@ pub(crate) fn create() {
 
     // Render
 
-    contents.push_str("P3\n");
-    contents.push_str(&format!("{image_width} {image_height}\n"));
-    contents.push_str("255\n");
+    let mut framebuffer = Framebuffer::new(image_width, image_height);
 
     for j in 0..image_height {
         print!("\rScanlines remaining: {} ", image_height - j);
@@ -25,12 +22,11 @@ pub(crate) fn create() {
                 j as f64 / (image_height as f64 - 1.0),
                 0.0,
             );
-            contents.push_str(&format!("{pixel_color}\n"));
+            framebuffer.set_pixel(i, j, pixel_color);
         }
     }
 
-    fs::create_dir_all("out").expect("Unable to create directory");
-    fs::write("out/image.ppm", contents).expect("Unable to write file");
+    framebuffer.save("out/image.png").expect("Unable to write file");
 
     print!("\rDone.                 \n");
 }
@@ -0,0 +1,146 @@
+//! Renders every chapter of the book, one image per step, into out/.
+//!
+//!     cargo run --release --example chapters
+
+use std::io;
+use std::sync::Arc;
+use engine::background::Background;
+use engine::camera::Camera;
+use engine::hittable::HittableList;
+use engine::integrator::{Diffuse, Normals, PathTracer};
+use engine::material::{self, Material};
+use engine::scene;
+use engine::sphere::Sphere;
+use engine::vec3::{Color, Point3, Vec3};
+
+mod image;
+mod old_camera;
+mod hit_sphere;
+mod surface_normals_and_multiple_objects;
+mod world;
+
+fn main() -> io::Result<()> {
+    // https://raytracing.github.io/books/RayTracingInOneWeekend.html#outputanimage
+    // https://raytracing.github.io/books/RayTracingInOneWeekend.html#thevec3class
+    image::create();
+
+    // https://raytracing.github.io/books/RayTracingInOneWeekend.html#rays,asimplecamera,andbackground
+    old_camera::render(old_camera::ray_color, "out/ray_color.png");
+
+    // https://raytracing.github.io/books/RayTracingInOneWeekend.html#addingasphere
+    old_camera::render(hit_sphere::ray_color, "out/sphere.png");
+
+    // https://raytracing.github.io/books/RayTracingInOneWeekend.html#surfacenormalsandmultipleobjects
+    old_camera::render(surface_normals_and_multiple_objects::ray_color, "out/sphere_normal.png");
+    world::render("out/world.png");
+
+    // https://raytracing.github.io/books/RayTracingInOneWeekend.html#movingcameracodeintoitsownclass
+    let sky = Background::sky();
+    // Nothing in the first book emits light except the sky, so there are no lights to sample.
+    let path_tracer = PathTracer::new(HittableList::new());
+    let mut world = HittableList::new();
+    let mat : Arc<dyn Material> = Arc::new(material::Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, 0.0, -1.0), 0.5, Some(Arc::clone(&mat)))));
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, -100.5, -1.0), 100.0, Some(Arc::clone(&mat)))));
+    let mut camera = Camera::new(400, 16.0 / 9.0, 100, 0);
+    camera.render(&world, &sky, "out/camera.png", &Normals, false, false)?;
+
+    // https://raytracing.github.io/books/RayTracingInOneWeekend.html#antialiasing
+    camera.samples_per_pixel = 10;
+    camera.render(&world, &sky, "out/anti_aliasing.png", &Normals, true, false)?;
+
+    // https://raytracing.github.io/books/RayTracingInOneWeekend.html#diffusematerials
+    camera.max_depth = 50;
+    camera.render(&world, &sky, "out/diffuse.png", &Diffuse, true, false)?;
+    camera.render(&world, &sky, "out/lambertian_diffuse.png", &path_tracer, true, false)?;
+    camera.render(&world, &sky, "out/gamma_diffuse.png", &path_tracer, true, true)?;
+
+    // https://raytracing.github.io/books/RayTracingInOneWeekend.html#metal
+    let material_ground: Arc<dyn Material>   = Arc::new(material::Lambertian::new(
+        Color::new(0.8, 0.8, 0.0)));
+    let material_center : Arc<dyn Material>  = Arc::new(material::Lambertian::new(
+        Color::new(0.7, 0.3, 0.3)));
+    let material_left : Arc<dyn Material>  = Arc::new(material::Metal::new(
+        Color::new(0.8, 0.8, 0.8), 0.0));
+    let material_right : Arc<dyn Material>  = Arc::new(material::Metal::new(
+        Color::new(0.8, 0.6, 0.2), 0.0));
+    let mut world = HittableList::new();
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, -100.5, -1.0), 100.0,
+                                  Some(Arc::clone(&material_ground)))));
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, 0.0, -1.0), 0.5,
+                                  Some(Arc::clone(&material_center)))));
+    world.add(Arc::new(Sphere::new(Point3::new(-1.0, 0.0, -1.0), 0.5,
+                                  Some(Arc::clone(&material_left)))));
+    world.add(Arc::new(Sphere::new(Point3::new(1.0, 0.0, -1.0), 0.5,
+                                  Some(Arc::clone(&material_right)))));
+    let mut cam = Camera::new(400, 16.0 / 9.0, 100, 50);
+
+    cam.render(&world, &sky, "out/metal.png", &path_tracer, true, true)?;
+
+    // https://raytracing.github.io/books/RayTracingInOneWeekend.html#metal/fuzzyreflection
+    let material_left: Arc<dyn Material> = Arc::new(material::Metal::new(
+        Color::new(0.8, 0.8, 0.8), 0.3));
+    let material_right: Arc<dyn Material> = Arc::new(material::Metal::new(
+        Color::new(0.8, 0.6, 0.2), 1.0));
+    let mut world = HittableList::new();
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, -100.5, -1.0), 100.0,
+                                  Some(Arc::clone(&material_ground)))));
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, 0.0, -1.0), 0.5,
+                                  Some(Arc::clone(&material_center)))));
+    world.add(Arc::new(Sphere::new(Point3::new(-1.0, 0.0, -1.0), 0.5,
+                                  Some(Arc::clone(&material_left)))));
+    world.add(Arc::new(Sphere::new(Point3::new(1.0, 0.0, -1.0), 0.5,
+                                  Some(Arc::clone(&material_right)))));
+
+    cam.render(&world, &sky, "out/fuzzed_metal.png", &path_tracer, true, true)?;
+
+    // https://raytracing.github.io/books/RayTracingInOneWeekend.html#dielectrics
+    let material_ground: Arc<dyn Material> = Arc::new(material::Lambertian::new(
+        Color::new(0.8, 0.8, 0.0)));
+    let material_center: Arc<dyn Material> = Arc::new(material::Lambertian::new(
+        Color::new(0.1, 0.2, 0.5)));
+    let material_left: Arc<dyn Material> = Arc::new(material::Dielectric::new(1.5));
+    let material_right: Arc<dyn Material> = Arc::new(material::Metal::new(
+        Color::new(0.8, 0.6, 0.2), 0.0));
+    let mut world = HittableList::new();
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, -100.5, -1.0), 100.0,
+                                  Some(Arc::clone(&material_ground)))));
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, 0.0, -1.0), 0.5,
+                                  Some(Arc::clone(&material_center)))));
+    world.add(Arc::new(Sphere::new(Point3::new(-1.0, 0.0, -1.0), 0.5,
+                                  Some(Arc::clone(&material_left)))));
+    // A sphere with a negative radius has its normals pointing inward, so nesting it inside the
+    // glass sphere makes a hollow glass bubble.
+    world.add(Arc::new(Sphere::new(Point3::new(-1.0, 0.0, -1.0), -0.4,
+                                  Some(Arc::clone(&material_left)))));
+    world.add(Arc::new(Sphere::new(Point3::new(1.0, 0.0, -1.0), 0.5,
+                                  Some(Arc::clone(&material_right)))));
+
+    cam.render(&world, &sky, "out/dielectric.png", &path_tracer, true, true)?;
+
+    // https://raytracing.github.io/books/RayTracingInOneWeekend.html#positionablecamera
+    cam.lookfrom = Point3::new(-2.0, 2.0, 1.0);
+    cam.lookat = Point3::new(0.0, 0.0, -1.0);
+    cam.vup = Vec3::new(0.0, 1.0, 0.0);
+    cam.vfov = 90.0;
+    cam.render(&world, &sky, "out/positionable_camera.png", &path_tracer, true, true)?;
+    cam.vfov = 20.0;
+    cam.render(&world, &sky, "out/positionable_camera_zoom.png", &path_tracer, true, true)?;
+
+    // https://raytracing.github.io/books/RayTracingInOneWeekend.html#defocusblur
+    cam.defocus_angle = 10.0;
+    cam.focus_dist = 3.4;
+    cam.render(&world, &sky, "out/defocus_blur.png", &path_tracer, true, true)?;
+
+    // The same scene, described in a scene file instead of code.
+    let mut scene = scene::load("scenes/defocus_blur.toml").expect("Unable to load scene");
+    scene.camera.render(&scene.world, &scene.background, "out/defocus_blur_scene.png",
+                        &path_tracer, true, true)?;
+
+    // https://raytracing.github.io/books/RayTracingInOneWeekend.html#wherenext?/afinalrender
+    // https://raytracing.github.io/books/RayTracingTheNextWeek.html#boundingvolumehierarchies
+    let mut scene = scene::builtin("final", &mut engine::new_rng(None)).unwrap();
+    scene.camera.render(&scene.world, &scene.background, "out/final_scene.png", &path_tracer,
+                        true, true)?;
+    Ok(())
+}
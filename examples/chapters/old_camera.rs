@@ -1,9 +1,10 @@
 use std::cmp::max;
-use std::{fs, io};
+use std::io;
 use std::io::Write;
-use std::path::Path;
-use crate::ray::Ray;
-use crate::vec3::{Color, unit_vector, Point3, Vec3};
+use engine::background::Background;
+use engine::output::Framebuffer;
+use engine::ray::Ray;
+use engine::vec3::{Color, Point3, Vec3};
 
 /// Simple gradient via linear interpolation
 /// blended value = (1-a) * white + a * blue
@@ -15,16 +16,15 @@ use crate::vec3::{Color, unit_vector, Point3, Vec3};
 ///
 /// Because we're looking at the height of y after normalization (y os dependent on x and z in
 /// normalization), there will be a horizontal gradient in addition the vertical gradient.
-pub(crate) fn ray_color(r: &Ray) -> Color {
-    let unit_direction = unit_vector(r.direction);
-    let a = 0.5 * (unit_direction.y + 1.0);
-    return (1.0 - a) * Color::new(1.0, 1.0, 1.0) + a * Color::new(0.5, 0.7, 1.0);
+pub fn ray_color(r: &Ray) -> Color {
+    // The first version of the sky in the book is a slightly different blue.
+    let sky = Background::Gradient { bottom: Color::new(1.0, 1.0, 1.0),
+                                     top: Color::new(0.5, 0.7, 1.0) };
+    sky.value(r)
 }
 
 /// https://raytracing.github.io/books/RayTracingInOneWeekend.html#rays,asimplecamera,andbackground
-pub(crate) fn render(ray_color: fn(&Ray) -> Color, file_path: &str) {
-    let mut contents = String::with_capacity(2_000_000);
-
+pub fn render(ray_color: fn(&Ray) -> Color, file_path: &str) {
     // Image
 
     let aspect_ratio: f64 = 16.0 / 9.0;
@@ -97,9 +97,7 @@ pub(crate) fn render(ray_color: fn(&Ray) -> Color, file_path: &str) {
 
     // Render
 
-    contents.push_str("P3\n");
-    contents.push_str(&format!("{image_width} {image_height}\n"));
-    contents.push_str("255\n");
+    let mut framebuffer = Framebuffer::new(image_width as usize, image_height as usize);
 
     for j in 0..image_height {
         print!("\rScanlines remaining: {} ", image_height - j);
@@ -110,13 +108,11 @@ pub(crate) fn render(ray_color: fn(&Ray) -> Color, file_path: &str) {
             let r = Ray::new(camera_center, ray_direction);
 
             let pixel_color = ray_color(&r);
-            contents.push_str(&format!("{pixel_color}\n"));
+            framebuffer.set_pixel(i as usize, j as usize, pixel_color);
         }
     }
 
-    let directory = Path::new(&file_path).parent().unwrap().to_str().unwrap();
-    fs::create_dir_all(directory).expect("Unable to create directory");
-    fs::write(file_path, contents).expect("Unable to write file");
+    framebuffer.save(file_path).expect("Unable to write file");
 
     print!("\rDone.                 \n");
 }
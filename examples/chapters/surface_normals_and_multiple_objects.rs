@@ -20,8 +20,9 @@
 // Only 1 sphere in the scene, so don't have to worry about negative values of t.
 // Assume the closest hit point (smallest t) is the one we want.
 
-use crate::ray::Ray;
-use crate::vec3::{Color, dot, unit_vector, Point3};
+use engine::background::Background;
+use engine::ray::Ray;
+use engine::vec3::{Color, dot, unit_vector, Point3};
 
 fn hit_sphere(center: Point3, radius: f64, r: &Ray) -> f64 {
     let delta = r.origin - center;
@@ -46,7 +47,7 @@ fn hit_sphere(center: Point3, radius: f64, r: &Ray) -> f64 {
     if discriminant < 0.0 { -1.0 } else { -half_b - f64::sqrt(discriminant) / a }
 }
 
-pub(crate) fn ray_color(r: &Ray) -> Color {
+pub fn ray_color(r: &Ray) -> Color {
     let center = Point3::new(0.0, 0.0, -1.0);
     let t = hit_sphere(center, 0.5, r);
     if t > 0.0  {
@@ -54,7 +55,5 @@ pub(crate) fn ray_color(r: &Ray) -> Color {
         return 0.5 * Color::new(n.x + 1.0, n.y + 1.0, n.z + 1.0);
     }
 
-    let unit_direction = unit_vector(r.direction);
-    let a = 0.5 * (unit_direction.y + 1.0);
-    (1.0 - a) * Color::new(1.0, 1.0, 1.0) + a * Color::new(0.6, 0.7, 1.0)
+    Background::sky().value(r)
 }
@@ -1,29 +1,25 @@
 use std::cmp::max;
-use std::{fs, io};
+use std::io;
 use std::io::Write;
-use std::path::Path;
-use std::rc::Rc;
-use crate::hittable::{HitRecord, Hittable, HittableList};
-use crate::interval::Interval;
-use crate::ray::Ray;
-use crate::sphere::Sphere;
-use crate::vec3::{Color, unit_vector, Point3, Vec3};
-
-pub(crate) fn ray_color(r: &Ray, world: &dyn Hittable) -> Color {
+use std::sync::Arc;
+use engine::background::Background;
+use engine::hittable::{HitRecord, Hittable, HittableList};
+use engine::interval::Interval;
+use engine::output::Framebuffer;
+use engine::ray::Ray;
+use engine::sphere::Sphere;
+use engine::vec3::{Color, Point3, Vec3};
+
+pub fn ray_color(r: &Ray, world: &dyn Hittable) -> Color {
     let mut rec = HitRecord::default();
     if world.hit(r, Interval::new(0.0, f64::INFINITY), &mut rec) {
         return 0.5 * (rec.normal.unwrap() + Color::new(1.0, 1.0, 1.0));
     }
 
-    let unit_direction = unit_vector(r.direction);
-    let a = 0.5*(unit_direction.y + 1.0);
-
-    (1.0 - a) * Color::new(1.0, 1.0, 1.0) + a * Color::new(0.6, 0.7, 1.0)
+    Background::sky().value(r)
 }
 
-pub(crate) fn render(file_path: &str) {
-    let mut contents = String::with_capacity(2_000_000);
-
+pub fn render(file_path: &str) {
     // Image
 
     let aspect_ratio: f64 = 16.0 / 9.0;
@@ -34,8 +30,8 @@ pub(crate) fn render(file_path: &str) {
 
     // World
     let mut world = HittableList::new();
-    world.add(Rc::new(Sphere::new(Point3::new(0.0, 0.0, -1.0), 0.5, None)));
-    world.add(Rc::new(Sphere::new(Point3::new(0.0, -100.5, -1.0), 100.0, None)));
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, 0.0, -1.0), 0.5, None)));
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, -100.5, -1.0), 100.0, None)));
 
     // Camera
 
@@ -101,9 +97,7 @@ pub(crate) fn render(file_path: &str) {
 
     // Render
 
-    contents.push_str("P3\n");
-    contents.push_str(&format!("{image_width} {image_height}\n"));
-    contents.push_str("255\n");
+    let mut framebuffer = Framebuffer::new(image_width as usize, image_height as usize);
 
     for j in 0..image_height {
         print!("\rScanlines remaining: {} ", image_height - j);
@@ -114,13 +108,11 @@ pub(crate) fn render(file_path: &str) {
             let r = Ray::new(camera_center, ray_direction);
 
             let pixel_color = ray_color(&r, &world);
-            contents.push_str(&format!("{pixel_color}\n"));
+            framebuffer.set_pixel(i as usize, j as usize, pixel_color);
         }
     }
 
-    let directory = Path::new(&file_path).parent().unwrap().to_str().unwrap();
-    fs::create_dir_all(directory).expect("Unable to create directory");
-    fs::write(file_path, contents).expect("Unable to write file");
+    framebuffer.save(file_path).expect("Unable to write file");
 
     print!("\rDone.                 \n");
 }
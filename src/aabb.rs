@@ -0,0 +1,103 @@
+use crate::interval::{self, Interval};
+use crate::ray::Ray;
+use crate::vec3::Point3;
+
+/// Axis-aligned bounding box, stored as one interval per axis.
+///
+/// Testing a ray against a box is much cheaper than testing it against whatever is inside, so if
+/// the ray misses the box we can skip everything it encloses. This is what makes a bounding
+/// volume hierarchy (see `bvh`) sub-linear in the number of objects.
+#[derive(Clone, Copy, Debug)]
+pub struct Aabb {
+    pub x: Interval,
+    pub y: Interval,
+    pub z: Interval,
+}
+
+impl Aabb {
+    // Treat the two points a and b as extrema for the bounding box, so we don't require a
+    // particular minimum/maximum coordinate order.
+    pub fn from_points(a: Point3, b: Point3) -> Self {
+        Aabb {
+            x: Interval::new(f64::min(a.x, b.x), f64::max(a.x, b.x)),
+            y: Interval::new(f64::min(a.y, b.y), f64::max(a.y, b.y)),
+            z: Interval::new(f64::min(a.z, b.z), f64::max(a.z, b.z)),
+        }.pad_to_minimums()
+    }
+
+    pub fn from_intervals(x: Interval, y: Interval, z: Interval) -> Self {
+        Aabb { x, y, z }.pad_to_minimums()
+    }
+
+    // Create the box tightly enclosing the two input boxes.
+    pub fn enclosing(box0: &Aabb, box1: &Aabb) -> Self {
+        Aabb {
+            x: Interval::enclosing(box0.x, box1.x),
+            y: Interval::enclosing(box0.y, box1.y),
+            z: Interval::enclosing(box0.z, box1.z),
+        }
+    }
+
+    // A flat object, like a triangle lying in an axis-aligned plane, has a box with no thickness
+    // along one axis, and the slab test below never reports a hit on an empty slab. Pad any side
+    // that is too thin so there's always something to hit.
+    fn pad_to_minimums(self) -> Self {
+        let delta = 0.0001;
+        let pad = |ax: Interval| if ax.size() < delta { ax.expand(delta) } else { ax };
+        Aabb { x: pad(self.x), y: pad(self.y), z: pad(self.z) }
+    }
+
+    pub fn axis(&self, n: usize) -> Interval {
+        match n {
+            0 => self.x,
+            1 => self.y,
+            2 => self.z,
+            _ => panic!("index out of bounds: the length is 3 but the index is {n}"),
+        }
+    }
+
+    // Returns the index of the longest axis of the bounding box.
+    pub fn longest_axis(&self) -> usize {
+        if self.x.size() > self.y.size() {
+            if self.x.size() > self.z.size() { 0 } else { 2 }
+        } else if self.y.size() > self.z.size() {
+            1
+        } else {
+            2
+        }
+    }
+
+    // The "slab method": an n-dimensional box is the intersection of n axis-aligned intervals
+    // (slabs). For each axis we solve P(t) = A + tb for the two planes bounding the slab:
+    //     t0 = (x0 - A_x) / b_x
+    //     t1 = (x1 - A_x) / b_x
+    // The ray is inside the box only where it is inside every slab at once, so we keep shrinking
+    // `ray_t` to the overlap and bail out as soon as it becomes empty.
+    //
+    // A zero direction component divides to +/- infinity, which still compares correctly, so we
+    // don't need to special case rays parallel to a slab.
+    pub fn hit(&self, r: &Ray, mut ray_t: Interval) -> bool {
+        for a in 0..3 {
+            let ax = self.axis(a);
+            let adinv = 1.0 / r.direction[a];
+
+            let t0 = (ax.min - r.origin[a]) * adinv;
+            let t1 = (ax.max - r.origin[a]) * adinv;
+
+            let (t0, t1) = if t0 < t1 { (t0, t1) } else { (t1, t0) };
+            if t0 > ray_t.min {
+                ray_t.min = t0;
+            }
+            if t1 < ray_t.max {
+                ray_t.max = t1;
+            }
+
+            if ray_t.max <= ray_t.min {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+pub const EMPTY: Aabb = Aabb { x: interval::EMPTY, y: interval::EMPTY, z: interval::EMPTY };
@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+use crate::output::Framebuffer;
+use crate::utils::derive_seed;
+use crate::vec3::{Color, Vec3};
+
+// Arbitrary output variables: images of what the camera rays first hit, rendered alongside the
+// picture itself. Compositing uses them to pick out or relight parts of an image, and denoisers
+// to tell edges in the scene from noise.
+//
+// They're made from the same camera rays as the picture, averaged the same way, so they line up
+// with it, blurred edges and all, even with defocus or motion blur. IDs can't be averaged, so a
+// pixel gets the IDs of the surface most of its rays hit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Aov {
+    // The distance from the camera to the hit point along the view direction, in world units.
+    Depth,
+    // The world space surface normal, facing the camera.
+    Normal,
+    // The surface color, before any lighting.
+    Albedo,
+    // The materials, numbered from 1 in the order they first show up, left to right and top to
+    // bottom.
+    MaterialId,
+    // The objects, numbered from 1 in the order they first show up like the materials. A box,
+    // mesh or transformed instance is one object.
+    ObjectId,
+}
+
+/// Every AOV there is, in the order they're listed and written.
+pub const AOVS: [Aov; 5] = [Aov::Depth, Aov::Normal, Aov::Albedo, Aov::MaterialId, Aov::ObjectId];
+
+impl Aov {
+    pub fn name(self) -> &'static str {
+        match self {
+            Aov::Depth => "depth",
+            Aov::Normal => "normal",
+            Aov::Albedo => "albedo",
+            Aov::MaterialId => "material_id",
+            Aov::ObjectId => "object_id",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Aov> {
+        AOVS.into_iter().find(|aov| aov.name() == name)
+    }
+}
+
+/// The AOVs of a render, one value per pixel of each, row by row from the top left. Rays that hit
+/// nothing count as a zero normal and the background as albedo. A pixel none of whose rays hit
+/// anything has an infinite depth, and one where most rays hit nothing has 0 IDs.
+pub struct AovBuffers {
+    width: usize,
+    height: usize,
+    pub depth: Vec<f64>,
+    pub normal: Vec<Vec3>,
+    pub albedo: Vec<Color>,
+    pub material_id: Vec<u32>,
+    pub object_id: Vec<u32>,
+}
+
+impl AovBuffers {
+    pub fn new(width: usize, height: usize) -> Self {
+        let count = width * height;
+        AovBuffers {
+            width,
+            height,
+            depth: vec![f64::INFINITY; count],
+            normal: vec![Vec3::default(); count],
+            albedo: vec![Color::default(); count],
+            material_id: vec![0; count],
+            object_id: vec![0; count],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// An image of one AOV to look at. The values are made to fit in an 8-bit image: depth goes
+    /// from white at the camera through mid-gray at the median depth towards black far away,
+    /// normals are mapped from [-1, 1] to [0, 1], and every ID gets its own color. Pixels that
+    /// hit nothing are black, except in the albedo, which is gamma encoded like the render.
+    pub fn to_framebuffer(&self, aov: Aov, gamma_correction: bool) -> Framebuffer {
+        // The median rather than the farthest depth, which would be on the horizon if there's a
+        // plane.
+        let mut depths: Vec<f64> = self.depth.iter().copied().filter(|d| d.is_finite()).collect();
+        depths.sort_by(f64::total_cmp);
+        let median_depth = depths.get(depths.len() / 2).copied().unwrap_or(1.0);
+        let pixels = (0..self.width * self.height)
+            .map(|index| {
+                let hit = self.depth[index].is_finite();
+                match aov {
+                    _ if !hit && aov != Aov::Albedo => Color::new(0.0, 0.0, 0.0),
+                    Aov::Depth => {
+                        let gray = median_depth / (median_depth + self.depth[index]);
+                        Color::new(gray, gray, gray)
+                    }
+                    Aov::Normal => 0.5 * (self.normal[index] + Color::new(1.0, 1.0, 1.0)),
+                    Aov::Albedo => self.albedo[index],
+                    Aov::MaterialId => id_color(self.material_id[index]),
+                    Aov::ObjectId => id_color(self.object_id[index]),
+                }
+            })
+            .collect();
+
+        let mut framebuffer = Framebuffer::from_pixels(self.width, self.height, pixels);
+        framebuffer.gamma_correction = gamma_correction && aov == Aov::Albedo;
+        framebuffer
+    }
+
+    /// The AOVs as OpenEXR channels with their raw values, for `Framebuffer::save_with_layers`.
+    /// Each is a layer named after it: `depth.Z`, `normal.X`, `normal.Y`, `normal.Z`, `albedo.R`,
+    /// `albedo.G`, `albedo.B`, `material_id.id` and `object_id.id`.
+    pub fn exr_layers(&self, aovs: &[Aov]) -> Vec<(&'static str, Vec<f32>)> {
+        let mut layers = Vec::new();
+        for aov in aovs {
+            let values = |value: &dyn Fn(usize) -> f64| -> Vec<f32> {
+                (0..self.width * self.height).map(|index| value(index) as f32).collect()
+            };
+            match aov {
+                Aov::Depth => layers.push(("depth.Z", values(&|i| self.depth[i]))),
+                Aov::Normal => layers.extend([("normal.X", values(&|i| self.normal[i].x)),
+                                              ("normal.Y", values(&|i| self.normal[i].y)),
+                                              ("normal.Z", values(&|i| self.normal[i].z))]),
+                Aov::Albedo => layers.extend([("albedo.R", values(&|i| self.albedo[i].x)),
+                                              ("albedo.G", values(&|i| self.albedo[i].y)),
+                                              ("albedo.B", values(&|i| self.albedo[i].z))]),
+                Aov::MaterialId => {
+                    layers.push(("material_id.id", values(&|i| self.material_id[i] as f64)));
+                }
+                Aov::ObjectId => {
+                    layers.push(("object_id.id", values(&|i| self.object_id[i] as f64)));
+                }
+            }
+        }
+        layers
+    }
+}
+
+// One pixel's AOVs, before the IDs are numbered: until then, materials and objects go by their
+// addresses, with 0 for none.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct AovPixel {
+    depth: f64,
+    normal: Vec3,
+    albedo: Color,
+    material: usize,
+    object: usize,
+}
+
+// Adds up what each of a pixel's camera rays hit first into its `AovPixel`.
+#[derive(Default)]
+pub(crate) struct AovSamples {
+    misses: u32,
+    hits: u32,
+    depth: f64,
+    normal: Vec3,
+    albedo: Color,
+    // How many rays hit each material and object pair.
+    surfaces: Vec<((usize, usize), u32)>,
+}
+
+impl AovSamples {
+    pub(crate) fn add_miss(&mut self, background: Color) {
+        self.misses += 1;
+        self.albedo += background;
+    }
+
+    pub(crate) fn add_hit(&mut self, depth: f64, normal: Vec3, albedo: Color, material: usize,
+                          object: usize) {
+        self.hits += 1;
+        self.depth += depth;
+        self.normal += normal;
+        self.albedo += albedo;
+        match self.surfaces.iter_mut().find(|(surface, _)| *surface == (material, object)) {
+            Some((_, count)) => *count += 1,
+            None => self.surfaces.push(((material, object), 1)),
+        }
+    }
+
+    pub(crate) fn pixel(&self) -> AovPixel {
+        let samples = f64::from(self.misses + self.hits);
+        let (material, object) = self.surfaces.iter()
+            .filter(|(_, count)| *count >= self.misses)
+            .max_by_key(|(_, count)| *count)
+            .map_or((0, 0), |(surface, _)| *surface);
+        AovPixel {
+            depth: if self.hits > 0 { self.depth / f64::from(self.hits) } else { f64::INFINITY },
+            normal: self.normal / samples,
+            albedo: self.albedo / samples,
+            material,
+            object,
+        }
+    }
+}
+
+impl AovBuffers {
+    // Numbers the materials and objects from 1 in the order they first show up in `pixels`, so
+    // the IDs only depend on the image.
+    pub(crate) fn from_pixels(width: usize, height: usize, pixels: &[AovPixel]) -> Self {
+        let mut aov_buffers = AovBuffers::new(width, height);
+        let mut material_ids = HashMap::new();
+        let mut object_ids = HashMap::new();
+        for (index, pixel) in pixels.iter().enumerate() {
+            aov_buffers.depth[index] = pixel.depth;
+            aov_buffers.normal[index] = pixel.normal;
+            aov_buffers.albedo[index] = pixel.albedo;
+            aov_buffers.material_id[index] = number(&mut material_ids, pixel.material);
+            aov_buffers.object_id[index] = number(&mut object_ids, pixel.object);
+        }
+        aov_buffers
+    }
+}
+
+// The number of `key` in `numbers`, giving it the next one if it's new. 0 is always 0.
+fn number(numbers: &mut HashMap<usize, u32>, key: usize) -> u32 {
+    if key == 0 {
+        return 0;
+    }
+    let next = numbers.len() as u32 + 1;
+    *numbers.entry(key).or_insert(next)
+}
+
+// A color for an ID that's unlikely to look like its neighbours', picked by hashing it. 0, for
+// nothing, is black.
+fn id_color(id: u32) -> Color {
+    if id == 0 {
+        return Color::new(0.0, 0.0, 0.0);
+    }
+    let hash = derive_seed(0, id as u64);
+    let component = |shift: u32| 0.2 + 0.8 * ((hash >> shift) & 0xff) as f64 / 255.0;
+    Color::new(component(0), component(8), component(16))
+}
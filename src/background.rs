@@ -0,0 +1,55 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+use crate::output::Framebuffer;
+use crate::ray::Ray;
+use crate::vec3::{Color, unit_vector};
+
+/// What a ray sees when it leaves the scene without hitting anything. In scenes without lights
+/// this is the only source of light.
+#[derive(Clone)]
+pub enum Background {
+    Solid(Color),
+    // Blends from `bottom` straight down to `top` straight up, by the height of the direction.
+    Gradient { bottom: Color, top: Color },
+    // An equirectangular (latitude-longitude) image wrapped around the whole scene, usually a
+    // high dynamic range photograph of a real environment.
+    Environment(Arc<Framebuffer>),
+}
+
+impl Background {
+    // The white to light blue sky the book uses.
+    pub fn sky() -> Self {
+        Background::Gradient { bottom: Color::new(1.0, 1.0, 1.0), top: Color::new(0.6, 0.7, 1.0) }
+    }
+
+    pub fn value(&self, r: &Ray) -> Color {
+        let unit_direction = unit_vector(r.direction);
+        match self {
+            Background::Solid(color) => *color,
+            Background::Gradient { bottom, top } => {
+                let a = 0.5 * (unit_direction.y + 1.0);
+                (1.0 - a) * *bottom + a * *top
+            }
+            // An empty image, which `Framebuffer::load` never returns, gives no light at all.
+            Background::Environment(image) if image.width() == 0 || image.height() == 0 => {
+                Color::new(0.0, 0.0, 0.0)
+            }
+            Background::Environment(image) => {
+                // Longitude goes across the image, around the y axis starting from -x, and
+                // latitude down it from straight up to straight down. This is the same mapping
+                // the book uses for textures on spheres.
+                let u = (f64::atan2(-unit_direction.z, unit_direction.x) + PI) / (2.0 * PI);
+                let v = f64::acos(unit_direction.y.clamp(-1.0, 1.0)) / PI;
+                let i = ((u * image.width() as f64) as usize).min(image.width() - 1);
+                let j = ((v * image.height() as f64) as usize).min(image.height() - 1);
+                image.pixel(i, j)
+            }
+        }
+    }
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Self::sky()
+    }
+}
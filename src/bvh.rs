@@ -0,0 +1,96 @@
+use std::cmp::Ordering;
+use std::sync::Arc;
+use crate::aabb::{self, Aabb};
+use crate::hittable::{hit_child, HitRecord, Hittable, HittableList};
+use crate::interval::Interval;
+use crate::ray::Ray;
+use crate::utils::{random_double, SmallRng};
+use crate::vec3::{Point3, Vec3};
+
+/// A node of a bounding volume hierarchy.
+///
+/// Each node bounds its two children, which are either further nodes or the scene objects
+/// themselves. A ray that misses a node's box can skip the whole subtree, so a hit query costs
+/// roughly O(log n) box tests instead of testing every object in the list.
+pub struct BvhNode {
+    left: Arc<dyn Hittable>,
+    right: Arc<dyn Hittable>,
+    bbox: Aabb,
+}
+
+impl BvhNode {
+    /// Builds a hierarchy over the objects in `list`. An empty list makes a node that is never
+    /// hit.
+    pub fn new(list: HittableList) -> Self {
+        let mut objects = list.objects;
+        Self::build(&mut objects)
+    }
+
+    // We split the objects in two at every level:
+    //     1. Bound all the objects in the span.
+    //     2. Sort them along the longest axis of that box.
+    //     3. Put half in each subtree.
+    // Splitting along the longest axis keeps the two child boxes from overlapping much.
+    fn build(objects: &mut [Arc<dyn Hittable>]) -> Self {
+        let bbox = objects.iter()
+            .fold(aabb::EMPTY, |bbox, object| Aabb::enclosing(&bbox, &object.bounding_box()));
+
+        let axis = bbox.longest_axis();
+        let (left, right): (Arc<dyn Hittable>, Arc<dyn Hittable>) = match objects.len() {
+            // Only the root can be empty, since every split leaves at least one object per side.
+            0 => {
+                let empty: Arc<dyn Hittable> = Arc::new(HittableList::new());
+                (Arc::clone(&empty), empty)
+            }
+            1 => (Arc::clone(&objects[0]), Arc::clone(&objects[0])),
+            2 => (Arc::clone(&objects[0]), Arc::clone(&objects[1])),
+            len => {
+                objects.sort_by(|a, b| Self::box_compare(a, b, axis));
+                let (lower, upper) = objects.split_at_mut(len / 2);
+                (Arc::new(Self::build(lower)), Arc::new(Self::build(upper)))
+            }
+        };
+
+        BvhNode { left, right, bbox }
+    }
+
+    fn box_compare(a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>, axis: usize) -> Ordering {
+        let a_axis_interval = a.bounding_box().axis(axis);
+        let b_axis_interval = b.bounding_box().axis(axis);
+        a_axis_interval.min.total_cmp(&b_axis_interval.min)
+    }
+}
+
+impl Hittable for BvhNode {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        if !self.bbox.hit(r, ray_t) {
+            return false;
+        }
+
+        // If the left child was hit, the right child only matters if it's hit closer.
+        let hit_left = hit_child(self.left.as_ref(), r, ray_t, rec);
+        let right_t = Interval::new(ray_t.min, if hit_left { rec.t } else { ray_t.max });
+        let hit_right = hit_child(self.right.as_ref(), r, right_t, rec);
+
+        hit_left || hit_right
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    // Sampling picks either child with even odds, so a mesh can be used as a light. Unlike
+    // picking one object out of all of them, that favours objects in smaller subtrees, but the
+    // densities stay consistent, which is all that matters.
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        0.5 * self.left.pdf_value(origin, direction) + 0.5 * self.right.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: Point3, rng: &mut SmallRng) -> Vec3 {
+        if random_double!(rng) < 0.5 {
+            self.left.random(origin, rng)
+        } else {
+            self.right.random(origin, rng)
+        }
+    }
+}
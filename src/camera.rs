@@ -1,267 +1,386 @@
-use std::{fs, io};
+use std::{io, iter, thread};
 use std::cmp::max;
 use std::io::Write;
 use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::aov::{Aov, AovBuffers, AovPixel, AovSamples};
+use crate::background::Background;
+use crate::denoise;
 use crate::hittable::{HitRecord, Hittable};
+use crate::integrator::{self, Integrator};
 use crate::interval::Interval;
+use crate::output::Framebuffer;
 use crate::ray::Ray;
-use crate::utils::random_double;
-use crate::vec3::{Color, Point3, random_on_hemisphere, random_unit_vector, unit_vector, Vec3};
-
-pub(crate) struct Camera {
-    image_width: i32,
-    aspect_ratio: f64,
-    pub(crate) samples_per_pixel: i32,
-    pub(crate) max_depth: i32,
+use rand::SeedableRng;
+use crate::utils::{degrees_to_radians, derive_seed, random_double, SmallRng};
+use crate::vec3::{Color, cross, Point3, random_in_unit_disk, unit_vector, Vec3};
+
+// The color seen along a camera ray, and what the ray hit first added to the pixel's AOVs.
+type Trace<'a> = dyn Fn(&Ray, &mut SmallRng) -> Color + Sync + 'a;
+type FirstHit<'a> = dyn Fn(&Ray, &mut SmallRng, &mut AovSamples) + Sync + 'a;
+
+pub struct Camera {
+    // Rendered image width in pixel count.
+    pub image_width: i32,
+    // Ratio of image width over height.
+    pub aspect_ratio: f64,
+    pub samples_per_pixel: i32,
+    pub max_depth: i32,
+    // Vertical view angle (field of view) in degrees.
+    pub vfov: f64,
+    // Point the camera is looking from.
+    pub lookfrom: Point3,
+    // Point the camera is looking at.
+    pub lookat: Point3,
+    // Camera-relative "up" direction.
+    pub vup: Vec3,
+    // Variation angle of rays through each pixel.
+    pub defocus_angle: f64,
+    // Distance from camera lookfrom point to plane of perfect focus.
+    pub focus_dist: f64,
+    // The times the shutter opens and closes. Each ray is sent at a random time in between, so
+    // anything that moves while the shutter is open gets blurred along its path. Both are 0 by
+    // default, taking the picture in an instant.
+    pub shutter: Interval,
+    // Number of worker threads to render with. Defaults to the number of available cores.
+    pub threads: usize,
+    // Seed for the random number generators. The same seed renders the same image bit for bit;
+    // `None` picks a fresh one every render.
+    pub seed: Option<u64>,
+    // Arbitrary output variables `render` writes along with the image, none by default.
+    pub aovs: Vec<Aov>,
+    // Whether `render` filters the noise out of the image before saving it, guided by the AOVs.
+    // That gets a clean picture out of far fewer samples per pixel, at the cost of fine detail.
+    pub denoise: bool,
     image_height: i32,
     center: Point3,
     pixel00_loc: Point3,
     pixel_delta_u: Vec3,
     pixel_delta_v: Vec3,
+    // Camera frame basis vectors.
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    // Defocus disk horizontal radius.
+    defocus_disk_u: Vec3,
+    // Defocus disk vertical radius.
+    defocus_disk_v: Vec3,
 }
 
 impl Camera {
-    pub(crate) fn new(image_width: i32, aspect_ratio: f64, samples_per_pixel: i32, max_depth: i32)
+    pub fn new(image_width: i32, aspect_ratio: f64, samples_per_pixel: i32, max_depth: i32)
                       -> Self {
-        let image_height: i32 = max((image_width as f64 / aspect_ratio).floor() as i32, 1);
-
-        let focal_length = 1.0;
-
-        let viewport_height = 2.0;
-        let viewport_width = viewport_height * (image_width as f64 / image_height as f64);
-
-        let viewport_u = Vec3::new(viewport_width, 0.0, 0.0);
-        let viewport_v = Vec3::new(0.0, -viewport_height, 0.0);
-
-        let pixel_delta_u = viewport_u / image_width as f64;
-        let pixel_delta_v = viewport_v / image_height as f64;
-
-        let center = Point3::new(0.0, 0.0, 0.0);
-
-        let viewport_upper_left = center
-            - Vec3::new(0.0, 0.0, focal_length)
-            - viewport_u / 2.0
-            - viewport_v / 2.0;
-
-        let pixel00_loc = viewport_upper_left + 0.5 * (pixel_delta_u + pixel_delta_v);
-
-        Camera {
+        let mut camera = Camera {
             image_width,
             aspect_ratio,
             samples_per_pixel,
             max_depth,
-            image_height,
-            center,
-            pixel00_loc,
-            pixel_delta_u,
-            pixel_delta_v,
-        }
+            vfov: 90.0,
+            lookfrom: Point3::new(0.0, 0.0, 0.0),
+            lookat: Point3::new(0.0, 0.0, -1.0),
+            vup: Vec3::new(0.0, 1.0, 0.0),
+            defocus_angle: 0.0,
+            focus_dist: 10.0,
+            shutter: Interval::new(0.0, 0.0),
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            seed: None,
+            aovs: Vec::new(),
+            denoise: false,
+            image_height: 0,
+            center: Point3::default(),
+            pixel00_loc: Point3::default(),
+            pixel_delta_u: Vec3::default(),
+            pixel_delta_v: Vec3::default(),
+            u: Vec3::default(),
+            v: Vec3::default(),
+            w: Vec3::default(),
+            defocus_disk_u: Vec3::default(),
+            defocus_disk_v: Vec3::default(),
+        };
+        camera.initialize();
+        camera
     }
 
-    pub(crate) fn render(&self, world: &dyn Hittable, file_path: &str,
-                         ray_color: fn(&Ray, &dyn Hittable, i32) -> Color,
-                         anti_aliasing: bool, gamma_correction: bool) {
-        let mut contents = String::with_capacity(2_000_000);
+    // Recomputes the viewport from the camera settings. The public settings may have been changed
+    // since construction, so this is run again before every render.
+    fn initialize(&mut self) {
+        self.image_height = max((self.image_width as f64 / self.aspect_ratio).floor() as i32, 1);
 
-        contents.push_str("P3\n");
-        contents.push_str(&format!("{} {}\n", self.image_width, self.image_height));
-        contents.push_str("255\n");
+        self.center = self.lookfrom;
 
-        // When a real camera takes a picture, there are usually no jagged edges, because edge pixels
-        // are a blend of some foreground and background. Unlike rendered images, a true image of the
-        // world has infinite resolution and is continuous. We can get a similar effect by averaging
-        // a bunch of samples for each pixel.
-        //
-        // With a single ray intersecting through the center of each pixel, we are performing point
-        // sampling.
-        //
-        // If a checkerboard consisted of an 8x8 grid of black and white tiles, but only four rays
-        // hit it, then all four rays may intersect with only black, white, or some combination of
-        // both. However, in the real world, when we perceive a checkerboard from far away it looks gray
-        // instead of sharp points of black or white.
-        //
-        // In other words, our eyes integrate (the continuous function of) light falling on a
-        // particular (discrete) region of our rendered image.
+        // With the camera center at the origin looking down -Z, the viewport spans from -h to h
+        // vertically where h = tan(theta / 2) * focus_dist and theta is the vertical field of view.
         //
-        // We don't want to integrate the center of the pixel multiple times, but instead the light
-        // falling around the pixel, and then integrate those samples to approximate a true
-        // continuous result.
-        //
-        // So how do we integrate the light falling around the pixel?
-        // One way is via gaussian blur: sample the light falling around the pixel and average their
-        // values together.
-        if anti_aliasing {
-            for j in 0..self.image_height {
-                print!("\rScanlines remaining: {} ", self.image_height - j);
-                io::stdout().flush().unwrap();
-                for i in 0..self.image_width {
-                    let mut pixel_color = Color::new(0.0, 0.0, 0.0);
-                    for _ in 0..self.samples_per_pixel {
-                        let r = self.get_ray(i, j);
-                        pixel_color += ray_color(&r, world, self.max_depth);
-                    }
-
-                    let scale = 1.0 / self.samples_per_pixel as f64;
-                    let intensity = Interval::new(0.000, 0.999);
+        // We place the viewport on the plane of perfect focus. Without defocus blur every ray
+        // still passes through the camera center, so the image doesn't depend on `focus_dist`.
+        let theta = degrees_to_radians(self.vfov);
+        let h = f64::tan(theta / 2.0);
+        let viewport_height = 2.0 * h * self.focus_dist;
+        let viewport_width = viewport_height * (self.image_width as f64 / self.image_height as f64);
+
+        // To orient the camera we build an orthonormal basis (u, v, w) describing its frame:
+        //     w points opposite the view direction (we keep the right-handed -Z convention),
+        //     u is the unit vector pointing to camera right,
+        //     v is the unit vector pointing to camera up.
+        // `vup` only needs to lie in the plane orthogonal to the view direction once projected,
+        // so any "up" that isn't parallel to the view direction will do.
+        self.w = unit_vector(self.lookfrom - self.lookat);
+        self.u = unit_vector(cross(self.vup, self.w));
+        self.v = cross(self.w, self.u);
+
+        let viewport_u = viewport_width * self.u;
+        let viewport_v = viewport_height * -self.v;
+
+        self.pixel_delta_u = viewport_u / self.image_width as f64;
+        self.pixel_delta_v = viewport_v / self.image_height as f64;
+
+        let viewport_upper_left = self.center
+            - (self.focus_dist * self.w)
+            - viewport_u / 2.0
+            - viewport_v / 2.0;
 
-                    let mut r = pixel_color.x * scale;
-                    let mut g = pixel_color.y * scale;
-                    let mut b = pixel_color.z * scale;
+        self.pixel00_loc = viewport_upper_left + 0.5 * (self.pixel_delta_u + self.pixel_delta_v);
 
-                    if gamma_correction {
-                        r = Camera::linear_to_gamma(r);
-                        g = Camera::linear_to_gamma(g);
-                        b = Camera::linear_to_gamma(b);
-                    }
+        // A real camera has a lens of some radius. Rays from the same point on the focus plane
+        // pass through every part of the lens and converge back to one point on the sensor, while
+        // points off that plane get smeared (defocus blur, or depth of field).
+        //
+        // We don't need to simulate the inside of the camera: starting rays from random points on
+        // a disk centered on the camera center, all aimed at the same point on the focus plane,
+        // gives the same effect. The disk's radius comes from the cone angle with its apex at the
+        // viewport center and its base on the camera center.
+        let defocus_radius = self.focus_dist * f64::tan(degrees_to_radians(self.defocus_angle / 2.0));
+        self.defocus_disk_u = self.u * defocus_radius;
+        self.defocus_disk_v = self.v * defocus_radius;
+    }
 
-                    let r = intensity.clamp(r);
-                    let g = intensity.clamp(g);
-                    let b = intensity.clamp(b);
+    // Any `aovs` go in the same file as extra layers if it's OpenEXR, and otherwise in images
+    // next to it, named after them, e.g. `out/final.depth.png` for `out/final.png`. Fails if any
+    // of the images can't be written, after rendering.
+    pub fn render(&mut self, world: &dyn Hittable, background: &Background, file_path: &str,
+                  integrator: &dyn Integrator, anti_aliasing: bool, gamma_correction: bool)
+                  -> io::Result<()> {
+        let with_aovs = self.denoise || !self.aovs.is_empty();
+        let (mut framebuffer, aov_buffers) = self.render_image(world, background, integrator,
+                                                               anti_aliasing, gamma_correction,
+                                                               with_aovs);
+        if self.denoise {
+            framebuffer = denoise::denoise(&framebuffer, aov_buffers.as_ref());
+        }
 
-                    contents.push_str(&format!("{0}\n", Color::new(r, g, b)));
+        if let Some(aov_buffers) = aov_buffers.filter(|_| !self.aovs.is_empty()) {
+            let path = Path::new(file_path);
+            let extension = path.extension().map_or(String::new(), |extension| {
+                extension.to_string_lossy().to_ascii_lowercase()
+            });
+            if extension == "exr" {
+                framebuffer.save_with_layers(file_path, &aov_buffers.exr_layers(&self.aovs))?;
+            } else {
+                framebuffer.save(file_path)?;
+                for &aov in &self.aovs {
+                    let aov_path = path.with_extension(format!("{}.{extension}", aov.name()));
+                    aov_buffers.to_framebuffer(aov, gamma_correction)
+                        .save(&aov_path.to_string_lossy())?;
                 }
             }
         } else {
-            for j in 0..self.image_height {
-                print!("\rScanlines remaining: {} ", self.image_height - j);
-                io::stdout().flush().unwrap();
-                for i in 0..self.image_width {
-                    let pixel_center = self.pixel00_loc + (i * self.pixel_delta_u) + (j * self.pixel_delta_v);
-                    //
-                    let ray_origin = self.center;
-                    let ray_direction = pixel_center - ray_origin;
-                    let r = Ray::new(ray_origin, ray_direction);
-
-                    let pixel_color = Self::ray_color(&r, world, self.max_depth);
-                    contents.push_str(&format!("{pixel_color}\n"));
-                }
-            }
+            framebuffer.save(file_path)?;
         }
 
-
-        let directory = Path::new(&file_path).parent().unwrap().to_str().unwrap();
-        fs::create_dir_all(directory).expect("Unable to create directory");
-        fs::write(file_path, contents).expect("Unable to write file");
-
         print!("\rDone.                 \n");
+        Ok(())
     }
 
-    pub(crate) fn ray_color(r: &Ray, world: &dyn Hittable, _max_depth: i32) -> Color {
-        let mut rec = HitRecord::default();
-        if world.hit(r, Interval::new(0.0, f64::INFINITY), &mut rec) {
-            return 0.5 * (rec.normal.unwrap() + Color::new(1.0, 1.0, 1.0));
-        }
+    /// Renders the image into memory instead of a file, e.g. to compare it against a reference.
+    pub fn render_framebuffer(&mut self, world: &dyn Hittable, background: &Background,
+                              integrator: &dyn Integrator, anti_aliasing: bool,
+                              gamma_correction: bool) -> Framebuffer {
+        self.render_image(world, background, integrator, anti_aliasing, gamma_correction, false).0
+    }
 
-        let unit_direction = unit_vector(r.direction);
-        let a = 0.5 * (unit_direction.y + 1.0);
+    /// Renders the image into memory along with every AOV, whatever `aovs` asks for. They're made
+    /// from the same rays as the image, which comes out the same as from `render_framebuffer`.
+    pub fn render_with_aovs(&mut self, world: &dyn Hittable, background: &Background,
+                            integrator: &dyn Integrator, anti_aliasing: bool,
+                            gamma_correction: bool) -> (Framebuffer, AovBuffers) {
+        let (framebuffer, aov_buffers) = self.render_image(world, background, integrator,
+                                                           anti_aliasing, gamma_correction, true);
+        (framebuffer, aov_buffers.expect("Unable to render AOVs"))
+    }
 
-        (1.0 - a) * Color::new(1.0, 1.0, 1.0) + a * Color::new(0.6, 0.7, 1.0)
+    fn render_image(&mut self, world: &dyn Hittable, background: &Background,
+                    integrator: &dyn Integrator, anti_aliasing: bool, gamma_correction: bool,
+                    with_aovs: bool) -> (Framebuffer, Option<AovBuffers>) {
+        self.initialize();
+
+        let seed = self.seed.unwrap_or_else(rand::random);
+        let trace = |r: &Ray, rng: &mut SmallRng| {
+            integrator.ray_color(r, world, background, self.max_depth, rng)
+        };
+        let first_hit = |r: &Ray, rng: &mut SmallRng, samples: &mut AovSamples| {
+            let mut rec = HitRecord::default();
+            if !world.hit(r, integrator::SHADOW_ACNE, &mut rec) {
+                samples.add_miss(background.value(r));
+                return;
+            }
+            let material = rec.mat.as_ref().map_or(0, |mat| Arc::as_ptr(mat).cast::<()>() as usize);
+            // Camera rays reach the focus plane at t = 1, wherever on the lens they start.
+            samples.add_hit(rec.t * self.focus_dist, rec.normal.unwrap(),
+                            integrator::surface_albedo(r, &rec, rng), material, rec.object_id);
+        };
+        let (pixels, aov_pixels) = self.render_pixels(&trace, with_aovs.then_some(&first_hit),
+                                                      anti_aliasing, seed);
+
+        let image_width = self.image_width as usize;
+        let image_height = self.image_height as usize;
+        let mut framebuffer = Framebuffer::from_pixels(image_width, image_height, pixels);
+        framebuffer.gamma_correction = gamma_correction;
+        let aov_buffers = with_aovs
+            .then(|| AovBuffers::from_pixels(image_width, image_height, &aov_pixels));
+        (framebuffer, aov_buffers)
     }
 
-    // Diffuse objects that don't emit their own light merely take on the color of their
-    // surroundings, but they do modulate with their own intrinsic color.
-    //
-    // Light that reflects off a diffuse surface has its direction randomized, so, if we send
-    // three rays into a crack between two diffuse surfaces they will each have different random
-    // behavior.
+    // Every pixel is independent of the others, so we hand out scanlines to one worker thread per
+    // core. Each worker grabs the next unrendered row until there are none left, which keeps the
+    // threads busy even when some rows (e.g. the ones full of glass) are much slower than others.
+    // Rows are written straight into their slot of the framebuffer, so the output stays in order.
     //
-    // They might also be absorbed rather than reflected.
+    // Which thread renders which row changes from run to run, so rather than giving each thread a
+    // generator, every pixel gets its own one seeded from the render seed and the pixel's index.
+    // That way a given seed always produces the same image, however many threads there are.
     //
-    // An algorithm that randomizes direction will produce surfaces that look matte.
-    // The simplest diffuse material is one in which it has an equal chance of reflecting light
-    // in any direction.
-    pub(crate) fn ray_color_diffuse(r: &Ray, world: &dyn Hittable, depth: i32) -> Color {
-        if depth <= 0 {
-            return Color::new(0.0, 0.0, 0.0);
-        }
-
-        let mut rec = HitRecord::default();
-
-        // 0.001 - fix shadow acne: bug associated with floating point rounding errors on object
-        // intersections
-        if world.hit(r, Interval::new(0.001, f64::INFINITY), &mut rec) {
-            let direction = random_on_hemisphere(rec.normal.unwrap());
-            return 0.5 * Self::ray_color_diffuse(&Ray::new(rec.p.unwrap(), direction), world,
-                                                 depth - 1);
-        }
+    // With `first_hit`, the AOVs are gathered from the same camera rays, with a generator of
+    // their own so that asking for them doesn't change the image.
+    fn render_pixels(&self, trace: &Trace, first_hit: Option<&FirstHit>, anti_aliasing: bool,
+                     seed: u64) -> (Vec<Color>, Vec<AovPixel>) {
+        let image_width = self.image_width as usize;
+        let image_height = self.image_height as usize;
+        let mut pixels = vec![Color::default(); image_width * image_height];
+        let aov_pixel_count = if first_hit.is_some() { pixels.len() } else { 0 };
+        let mut aov_pixels = vec![AovPixel::default(); aov_pixel_count];
+
+        let aov_rows = aov_pixels.chunks_mut(image_width).map(Some)
+            .chain(iter::repeat_with(|| None));
+        let rows = Mutex::new(pixels.chunks_mut(image_width).zip(aov_rows).enumerate());
+        let rows_done = AtomicUsize::new(0);
+
+        thread::scope(|s| {
+            for _ in 0..max(self.threads, 1) {
+                s.spawn(|| loop {
+                    let next = rows.lock().unwrap().next();
+                    let Some((j, (row, mut aov_row))) = next else { break };
+
+                    for (i, pixel) in row.iter_mut().enumerate() {
+                        let index = (j * image_width + i) as u64;
+                        let mut rng = SmallRng::seed_from_u64(derive_seed(seed, index));
+                        let mut aov_rng = SmallRng::seed_from_u64(derive_seed(!seed, index));
+                        let aovs = first_hit.map(|first_hit| (first_hit, &mut aov_rng));
+                        let (color, aov_pixel) = self.pixel_color(trace, aovs, anti_aliasing,
+                                                                  i as i32, j as i32, &mut rng);
+                        *pixel = color;
+                        if let (Some(aov_row), Some(aov_pixel)) = (aov_row.as_mut(), aov_pixel) {
+                            aov_row[i] = aov_pixel;
+                        }
+                    }
 
-        let unit_direction = unit_vector(r.direction);
-        let a = 0.5 * (unit_direction.y + 1.0);
+                    let done = rows_done.fetch_add(1, Ordering::Relaxed) + 1;
+                    print!("\rScanlines remaining: {} ", image_height - done);
+                    io::stdout().flush().unwrap();
+                });
+            }
+        });
 
-        (1.0 - a) * Color::new(1.0, 1.0, 1.0) + a * Color::new(0.6, 0.7, 1.0)
+        (pixels, aov_pixels)
     }
 
-    pub(crate) fn ray_color_lambertian_diffuse(r: &Ray, world: &dyn Hittable, depth: i32) -> Color {
-        // If we've exceeded the ray bounce limit, no more light is gathered.
-        if depth <= 0 {
-            return Color::new(0.0, 0.0, 0.0);
-        }
-
-        let mut rec = HitRecord::default();
+    // `trace` gives the color seen along one camera ray. With `aovs`, every ray's first hit is
+    // also added up into the pixel's AOVs, drawing any random numbers from their own generator.
+    fn pixel_color(&self, trace: &Trace, aovs: Option<(&FirstHit, &mut SmallRng)>,
+                   anti_aliasing: bool, i: i32, j: i32, rng: &mut SmallRng)
+                   -> (Color, Option<AovPixel>) {
+        let with_aovs = aovs.is_some();
+        let mut aov_samples = AovSamples::default();
+        let mut aovs = aovs;
+        let mut sample = |r: &Ray, rng: &mut SmallRng| {
+            if let Some((first_hit, aov_rng)) = aovs.as_mut() {
+                first_hit(r, aov_rng, &mut aov_samples);
+            }
+            trace(r, rng)
+        };
 
+        if !anti_aliasing {
+            let color = sample(&self.pixel_center_ray(i, j), rng);
+            return (color, with_aovs.then(|| aov_samples.pixel()));
+        }
 
-        // Lambertian reflection
-        // Creates scatters via a random distribution of unit vectors proportional to cos
-        // (theta) where theta is the angle between the reflected ray and the surface normal.
+        // When a real camera takes a picture, there are usually no jagged edges, because edge pixels
+        // are a blend of some foreground and background. Unlike rendered images, a true image of the
+        // world has infinite resolution and is continuous. We can get a similar effect by averaging
+        // a bunch of samples for each pixel.
         //
-        // The intersection point, P, of a ray to the surface of the sphere has exactly two
-        // sides: inside and outside the sphere.
+        // With a single ray intersecting through the center of each pixel, we are performing point
+        // sampling.
         //
-        // We can create two unit spheres tangent to tha surface:
-        //    one sphere in the direction of the surface's normal (P + n)
-        //    and one vice versa (P - n)
+        // If a checkerboard consisted of an 8x8 grid of black and white tiles, but only four rays
+        // hit it, then all four rays may intersect with only black, white, or some combination of
+        // both. However, in the real world, when we perceive a checkerboard from far away it looks gray
+        // instead of sharp points of black or white.
         //
-        // We want our reflections to reflect in the same direction as the ray's origin, so
-        // with our current implementation in mind, we'll only worry about the unit sphere in
-        // the direction of the surface normal.
+        // In other words, our eyes integrate (the continuous function of) light falling on a
+        // particular (discrete) region of our rendered image.
         //
-        // We then take a random point, S, on the unit radius of the selected sphere and send
-        // a ray originating from the contact point, P, to the generated point, S (this is
-        // the vector S - P).
+        // We don't want to integrate the center of the pixel multiple times, but instead the light
+        // falling around the pixel, and then integrate those samples to approximate a true
+        // continuous result.
         //
-        // let direction = rec.normal.unwrap() + random_unit_vector();
-        // return 0.5 * Self::ray_color_diffuse(&Ray::new(rec.p.unwrap(), direction), world,
-        //                                      depth - 1);
-
-        // 0.001 - fix shadow acne: bug associated with floating point rounding errors on object
-        // intersections
-        if world.hit(r, Interval::new(0.001, f64::INFINITY), &mut rec) {
-            let mut scattered = Ray::default();
-            let mut attenuation = Color::default();
-            if !rec.mat.is_none() && rec.mat.as_ref().unwrap().scatter(r, &rec, &mut attenuation,
-                                                                       &mut scattered) {
-                return attenuation * Self::ray_color_lambertian_diffuse(&scattered, world,
-                                                                        depth - 1);
-            }
-            return Color::new(0.0, 0.0, 0.0);
+        // So how do we integrate the light falling around the pixel?
+        // One way is via gaussian blur: sample the light falling around the pixel and average their
+        // values together.
+        let mut pixel_color = Color::new(0.0, 0.0, 0.0);
+        for _ in 0..self.samples_per_pixel {
+            let r = self.get_ray(i, j, rng);
+            pixel_color += sample(&r, rng);
         }
 
-        let unit_direction = unit_vector(r.direction);
-        let a = 0.5 * (unit_direction.y + 1.0);
+        (pixel_color / self.samples_per_pixel as f64, with_aovs.then(|| aov_samples.pixel()))
+    }
 
-        (1.0 - a) * Color::new(1.0, 1.0, 1.0) + a * Color::new(0.6, 0.7, 1.0)
+    // The ray from the camera center through the middle of pixel i, j, when the shutter opens.
+    fn pixel_center_ray(&self, i: i32, j: i32) -> Ray {
+        let pixel_center = self.pixel00_loc + (i * self.pixel_delta_u) + (j * self.pixel_delta_v);
+        let ray_direction = pixel_center - self.center;
+        Ray::with_time(self.center, ray_direction, self.shutter.min)
     }
 
-    fn get_ray(&self, i: i32, j: i32) -> Ray {
-        // Get a randomly sample camera ray for the pixel at location i, j.
+    fn get_ray(&self, i: i32, j: i32, rng: &mut SmallRng) -> Ray {
+        // Get a randomly-sampled camera ray for the pixel at location i, j, originating from
+        // the camera defocus disk, at a random time while the shutter is open.
 
         let pixel_center = self.pixel00_loc + (i * self.pixel_delta_u) + (j * self.pixel_delta_v);
-        let pixel_sample = pixel_center + self.pixel_sample_square();
-        let ray_origin = self.center;
+        let pixel_sample = pixel_center + self.pixel_sample_square(rng);
+        let ray_origin = if self.defocus_angle <= 0.0 { self.center } else { self.defocus_disk_sample(rng) };
         let ray_direction = pixel_sample - ray_origin;
+        let ray_time = if self.shutter.size() > 0.0 {
+            random_double!(rng, self.shutter.min, self.shutter.max)
+        } else {
+            self.shutter.min
+        };
 
-        Ray::new(ray_origin, ray_direction)
+        Ray::with_time(ray_origin, ray_direction, ray_time)
     }
 
-    fn pixel_sample_square(&self) -> Vec3 {
+    fn pixel_sample_square(&self, rng: &mut SmallRng) -> Vec3 {
         // Returns a random point in the square surrounding a pixel at the origin.
-        let px = -0.5 + random_double!();
-        let py = -0.5 + random_double!();
+        let px = -0.5 + random_double!(rng);
+        let py = -0.5 + random_double!(rng);
 
         (px * self.pixel_delta_u) + (py * self.pixel_delta_v)
     }
 
-    fn linear_to_gamma(linear_component: f64) -> f64 {
-        f64::sqrt(linear_component)
+    fn defocus_disk_sample(&self, rng: &mut SmallRng) -> Point3 {
+        // Returns a random point in the camera defocus disk.
+        let p = random_in_unit_disk(rng);
+        self.center + (p.x * self.defocus_disk_u) + (p.y * self.defocus_disk_v)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,197 @@
+use std::fmt;
+use std::path::Path;
+use engine::{Aov, AOVS};
+use engine::integrator::INTEGRATORS;
+use engine::scene::BUILTIN_SCENES;
+
+pub(crate) fn usage() -> String {
+    format!("\
+Usage: engine [OPTIONS] [SCENE]
+
+Renders SCENE, either the name of a built-in scene or the path to a scene file.
+Defaults to the `final` scene. Built-in scenes: {}.
+
+Options:
+  -o, --output <PATH>          Output image path [default: out/<scene>.png]
+  -f, --format <FORMAT>        Output format: ppm, png, hdr or exr [default: from the output path]
+  -w, --width <PIXELS>         Image width
+  -a, --aspect-ratio <RATIO>   Image aspect ratio, e.g. 1.5 or 16:9
+  -s, --samples <COUNT>        Samples per pixel
+  -d, --max-depth <COUNT>      Maximum number of ray bounces
+  -i, --integrator <NAME>      What to render: {} [default: path]
+      --aov <NAMES>            Comma separated extra images to write: {} or all
+      --denoise                Filter the noise out of the image
+  -t, --threads <COUNT>        Number of render threads [default: number of cores]
+      --seed <SEED>            Seed for the random number generators
+  -h, --help                   Print this help", BUILTIN_SCENES.join(", "), INTEGRATORS.join(", "),
+        AOVS.map(Aov::name).join(", "))
+}
+
+const FORMATS: [&str; 4] = ["ppm", "png", "hdr", "exr"];
+
+/// The command line options. Anything not given leaves the scene's own setting alone.
+pub(crate) struct Args {
+    pub(crate) scene: String,
+    pub(crate) output: String,
+    pub(crate) width: Option<i32>,
+    pub(crate) aspect_ratio: Option<f64>,
+    pub(crate) samples_per_pixel: Option<i32>,
+    pub(crate) max_depth: Option<i32>,
+    pub(crate) integrator: String,
+    pub(crate) aovs: Vec<Aov>,
+    pub(crate) denoise: bool,
+    pub(crate) threads: Option<usize>,
+    pub(crate) seed: Option<u64>,
+    pub(crate) help: bool,
+}
+
+#[derive(Debug)]
+pub(crate) struct ArgsError(String);
+
+impl fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ArgsError {}
+
+impl Args {
+    pub(crate) fn parse(args: impl IntoIterator<Item = String>) -> Result<Args, ArgsError> {
+        let mut scene = None;
+        let mut output = None;
+        let mut format = None;
+        let mut parsed = Args {
+            scene: String::new(),
+            output: String::new(),
+            width: None,
+            aspect_ratio: None,
+            samples_per_pixel: None,
+            max_depth: None,
+            integrator: "path".to_string(),
+            aovs: Vec::new(),
+            denoise: false,
+            threads: None,
+            seed: None,
+            help: false,
+        };
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            // Accept both `--width 400` and `--width=400`.
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag.to_string(),
+                                                                  Some(value.to_string())),
+                _ => (arg.clone(), None),
+            };
+            let mut value = || inline_value.clone().or_else(|| args.next())
+                .ok_or_else(|| ArgsError(format!("missing value for `{flag}`")));
+
+            match flag.as_str() {
+                "-o" | "--output" => output = Some(value()?),
+                "-f" | "--format" => {
+                    let value = value()?.to_ascii_lowercase();
+                    if !FORMATS.contains(&value.as_str()) {
+                        return Err(ArgsError(format!(
+                            "unsupported format `{value}`, expected one of {}",
+                            FORMATS.join(", "))));
+                    }
+                    format = Some(value);
+                }
+                "-w" | "--width" => parsed.width = Some(parse_positive(&flag, &value()?)?),
+                "-a" | "--aspect-ratio" => {
+                    parsed.aspect_ratio = Some(parse_aspect_ratio(&value()?)?);
+                }
+                "-s" | "--samples" => {
+                    parsed.samples_per_pixel = Some(parse_positive(&flag, &value()?)?);
+                }
+                "-d" | "--max-depth" => parsed.max_depth = Some(parse_positive(&flag, &value()?)?),
+                "-i" | "--integrator" => {
+                    let value = value()?;
+                    if !INTEGRATORS.contains(&value.as_str()) {
+                        return Err(ArgsError(format!(
+                            "unknown integrator `{value}`, expected one of {}",
+                            INTEGRATORS.join(", "))));
+                    }
+                    parsed.integrator = value;
+                }
+                "--aov" => {
+                    for name in value()?.split(',').map(str::trim) {
+                        let aovs = match name {
+                            "all" => AOVS.to_vec(),
+                            _ => vec![Aov::from_name(name).ok_or_else(|| ArgsError(format!(
+                                "unknown AOV `{name}`, expected one of {} or all",
+                                AOVS.map(Aov::name).join(", "))))?],
+                        };
+                        for aov in aovs {
+                            if !parsed.aovs.contains(&aov) {
+                                parsed.aovs.push(aov);
+                            }
+                        }
+                    }
+                }
+                "--denoise" => parsed.denoise = true,
+                "-t" | "--threads" => parsed.threads = Some(parse_positive(&flag, &value()?)?),
+                "--seed" => {
+                    let value = value()?;
+                    parsed.seed = Some(value.parse().map_err(|_| ArgsError(format!(
+                        "`--seed` expects a non-negative integer, got `{value}`")))?);
+                }
+                "-h" | "--help" => parsed.help = true,
+                _ if flag.starts_with('-') => {
+                    return Err(ArgsError(format!("unknown option `{flag}`")));
+                }
+                _ if scene.is_none() => scene = Some(arg),
+                _ => return Err(ArgsError(format!("unexpected argument `{arg}`"))),
+            }
+        }
+
+        parsed.scene = scene.unwrap_or_else(|| "final".to_string());
+        let output = output.unwrap_or_else(|| format!("out/{}.png", scene_stem(&parsed.scene)));
+        parsed.output = match format {
+            Some(format) => Path::new(&output).with_extension(format).to_string_lossy().into_owned(),
+            None => output,
+        };
+        let extension = Path::new(&parsed.output).extension()
+            .map(|extension| extension.to_string_lossy().to_ascii_lowercase());
+        if !extension.is_some_and(|extension| FORMATS.contains(&extension.as_str())) {
+            return Err(ArgsError(format!(
+                "can't tell the output format of `{}`, expected a .{} file or `--format`",
+                parsed.output, FORMATS.join(", ."))));
+        }
+
+        Ok(parsed)
+    }
+
+    /// Whether `scene` names a built-in scene rather than a scene file.
+    pub(crate) fn is_builtin_scene(&self) -> bool {
+        BUILTIN_SCENES.contains(&self.scene.as_str())
+    }
+}
+
+// A scene file's name without its directory and extension, to name the output image after.
+fn scene_stem(scene: &str) -> String {
+    Path::new(scene).file_stem().map_or_else(|| scene.to_string(),
+                                             |stem| stem.to_string_lossy().into_owned())
+}
+
+fn parse_positive<T: std::str::FromStr + PartialOrd + Default>(flag: &str, value: &str)
+                                                              -> Result<T, ArgsError> {
+    match value.parse::<T>() {
+        Ok(n) if n > T::default() => Ok(n),
+        _ => Err(ArgsError(format!("`{flag}` expects a positive integer, got `{value}`"))),
+    }
+}
+
+// Aspect ratios are usually written as a ratio like 16:9, but a plain number works too.
+fn parse_aspect_ratio(value: &str) -> Result<f64, ArgsError> {
+    let ratio = match value.split_once(':') {
+        Some((width, height)) => width.trim().parse::<f64>()
+            .and_then(|width| Ok(width / height.trim().parse::<f64>()?)),
+        None => value.parse::<f64>(),
+    };
+    match ratio {
+        Ok(ratio) if ratio.is_finite() && ratio > 0.0 => Ok(ratio),
+        _ => Err(ArgsError(format!("invalid aspect ratio `{value}`"))),
+    }
+}
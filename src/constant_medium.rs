@@ -0,0 +1,93 @@
+use std::sync::Arc;
+use rand::SeedableRng;
+use crate::aabb::Aabb;
+use crate::hittable::{object_id, HitRecord, Hittable};
+use crate::interval::{self, Interval};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::utils::{derive_seed, random_double, SmallRng};
+use crate::vec3::Vec3;
+
+// https://raytracing.github.io/books/RayTracingTheNextWeek.html#volumes
+// A volume of constant density, like fog or smoke, filling the inside of `boundary`.
+//
+// A ray going through the volume can scatter at any point inside it. The chance of scattering in
+// a small distance dL is density * dL, so the distance travelled before scattering follows an
+// exponential distribution. If that distance is past where the ray leaves the volume, there's no
+// hit and the ray carries on through.
+//
+// The boundary must be closed and convex, so a ray goes in and out of it at most once: spheres,
+// boxes, or instances of them.
+pub struct ConstantMedium {
+    boundary: Arc<dyn Hittable>,
+    neg_inv_density: f64,
+    // What happens to a ray that scatters inside the volume, usually `Isotropic`.
+    phase_function: Option<Arc<dyn Material>>,
+}
+
+impl ConstantMedium {
+    pub fn new(boundary: Arc<dyn Hittable>, density: f64,
+               phase_function: Option<Arc<dyn Material>>) -> ConstantMedium {
+        ConstantMedium { boundary, neg_inv_density: -1.0 / density, phase_function }
+    }
+}
+
+impl Hittable for ConstantMedium {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        // Find where the ray's line enters and leaves the boundary, even if that's behind the
+        // ray's origin, which happens when the ray starts inside the volume.
+        let mut rec1 = HitRecord::default();
+        let mut rec2 = HitRecord::default();
+        if !self.boundary.hit(r, interval::UNIVERSE, &mut rec1) {
+            return false;
+        }
+        if !self.boundary.hit(r, Interval::new(rec1.t + 0.0001, f64::INFINITY), &mut rec2) {
+            return false;
+        }
+
+        // Only the part of that inside `ray_t` counts.
+        let enter = f64::max(rec1.t, f64::max(ray_t.min, 0.0));
+        let exit = f64::min(rec2.t, ray_t.max);
+        if enter >= exit {
+            return false;
+        }
+
+        let ray_length = r.direction.length();
+        let distance_inside_boundary = (exit - enter) * ray_length;
+        let mut rng = ray_rng(r, [rec1.t, rec2.t, self.neg_inv_density]);
+        let hit_distance = self.neg_inv_density * f64::ln(random_double!(rng));
+        if hit_distance > distance_inside_boundary {
+            return false;
+        }
+
+        rec.t = enter + hit_distance / ray_length;
+        rec.p = Some(r.at(rec.t));
+        // A point in a volume has no surface, so the normal and side are arbitrary.
+        rec.normal = Some(Vec3::new(1.0, 0.0, 0.0));
+        rec.front_face = Some(true);
+        rec.mat = self.phase_function.as_ref().map(Arc::clone);
+        rec.object_id = object_id(self);
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.boundary.bounding_box()
+    }
+}
+
+// `hit` doesn't get a random number generator, since surfaces don't need one, so the scattering
+// distance is drawn from a generator seeded by the ray itself. Every bounce sends out a ray in a
+// new random direction, so that's as good as random, while keeping `hit` a function of its ray
+// alone and renders repeatable for a given seed.
+//
+// A ray can go through several volumes though, and each has to scatter it independently of the
+// others, or a ray that gets through one would get through them all. So the seed also takes
+// `volume`: where the ray enters and leaves this volume's boundary, and its density.
+fn ray_rng(r: &Ray, volume: [f64; 3]) -> SmallRng {
+    let components = [r.origin.x, r.origin.y, r.origin.z,
+                      r.direction.x, r.direction.y, r.direction.z, r.time];
+    let seed = components.iter().chain(&volume)
+        .fold(0, |seed, c| derive_seed(seed, c.to_bits()));
+    SmallRng::seed_from_u64(seed)
+}
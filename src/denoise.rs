@@ -0,0 +1,162 @@
+use crate::aov::AovBuffers;
+use crate::output::Framebuffer;
+use crate::vec3::{Color, Vec3};
+
+// https://jo.dreggn.org/home/2010_atrous.pdf
+// Edge-avoiding à-trous wavelet filtering (Dammertz et al. 2010).
+//
+// With few samples per pixel, a render is the right image plus a lot of noise. Neighbouring
+// pixels on the same surface should mostly agree, so averaging them gets rid of the noise, as
+// long as we don't average across the edges between surfaces and blur the picture.
+//
+// A big blur is made cheap by repeating a small 5x5 one with its taps spread further apart each
+// time: 1, 2, 4, 8 then 16 pixels ("à trous" is French for "with holes"). That covers a
+// 125 pixel wide area with 25 taps per pixel per pass. Each tap's weight is cut down the more
+// the neighbour differs from the pixel, in color and, when there are AOVs to go by, in normal
+// and albedo. The AOVs are noise free, so they find the edges even where the colors are too
+// noisy to tell. The color weight gets stricter every pass, since the colors get smoother.
+//
+// A firefly, a lone pixel that happened to find a bright light, differs too much from its
+// neighbours to be blurred into them, so those are dimmed to match their neighbourhood first.
+// Only pixels far brighter than the noise around them count, so highlights that are merely the
+// brightest spot in their neighbourhood are kept. Dimming the rest takes a little energy out of
+// the image, which is the usual price for getting rid of them.
+
+// Each pass blurs the last one's result with the B3 spline, which is close to a Gaussian.
+const KERNEL: [f64; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+const PASSES: u32 = 5;
+
+// How different two pixels can be before they stop blurring into each other: the bigger these
+// are, the smoother the result and the softer the edges.
+const SIGMA_COLOR: f64 = 0.2;
+const SIGMA_NORMAL: f64 = 0.3;
+const SIGMA_ALBEDO: f64 = 0.1;
+
+// Fireflies are looked for among the 24 pixels around each one, and have to be brighter than
+// this many standard deviations above their mean. A pixel in a corner still has 8 neighbours.
+const FIREFLY_SIGMAS: f64 = 3.0;
+const FIREFLY_MIN_NEIGHBOURS: usize = 8;
+
+/// Filters the noise out of a render. `guides` are AOVs of the same render: with them, edges
+/// between surfaces are kept even where the image is too noisy to see them; without, the image
+/// is filtered by its colors alone. Either way the result keeps `framebuffer`'s gamma setting.
+pub fn denoise(framebuffer: &Framebuffer, guides: Option<&AovBuffers>) -> Framebuffer {
+    let width = framebuffer.width();
+    let height = framebuffer.height();
+    if let Some(guides) = guides {
+        assert_eq!((guides.width(), guides.height()), (width, height),
+                   "AOVs don't match the {width}x{height} image");
+    }
+
+    // A NaN or infinite pixel would make every weight it takes part in NaN, and spread to the
+    // whole neighbourhood over the passes, so it's dropped to black and filtered like the rest.
+    let colors: Vec<Color> = (0..width * height)
+        .map(|index| framebuffer.pixel(index % width, index / width))
+        .map(finite_or_black)
+        .collect();
+    let mut colors: Vec<Color> = (0..width * height)
+        .map(|index| clamp_firefly(&colors, width, height, index))
+        .collect();
+
+    for pass in 0..PASSES {
+        let step = 1 << pass;
+        let sigma_color = SIGMA_COLOR / step as f64;
+        colors = (0..width * height)
+            .map(|index| filter_pixel(&colors, guides, width, height, index, step, sigma_color))
+            .collect();
+    }
+
+    let mut denoised = Framebuffer::from_pixels(width, height, colors);
+    denoised.gamma_correction = framebuffer.gamma_correction;
+    denoised
+}
+
+// One tap of the filter for the pixel at `index`, with its neighbours `step` pixels apart.
+fn filter_pixel(colors: &[Color], guides: Option<&AovBuffers>, width: usize, height: usize,
+                index: usize, step: usize, sigma_color: f64) -> Color {
+    let (i, j) = (index % width, index / width);
+    let color = tone_map(colors[index]);
+
+    let mut sum = Color::new(0.0, 0.0, 0.0);
+    let mut weight_sum = 0.0;
+    for (dy, ky) in KERNEL.iter().enumerate() {
+        for (dx, kx) in KERNEL.iter().enumerate() {
+            // Neighbours past the border are left out.
+            let (Some(qi), Some(qj)) = ((i + dx * step).checked_sub(2 * step),
+                                        (j + dy * step).checked_sub(2 * step)) else { continue };
+            if qi >= width || qj >= height {
+                continue;
+            }
+            let q = qj * width + qi;
+
+            let mut weight = kx * ky * gaussian(tone_map(colors[q]) - color, sigma_color);
+            if let Some(guides) = guides {
+                // Whether a ray hit anything at all is an edge too.
+                if guides.depth[index].is_finite() != guides.depth[q].is_finite() {
+                    continue;
+                }
+                weight *= gaussian(guides.normal[q] - guides.normal[index], SIGMA_NORMAL);
+                weight *= gaussian(guides.albedo[q] - guides.albedo[index], SIGMA_ALBEDO);
+            }
+
+            sum += weight * colors[q];
+            weight_sum += weight;
+        }
+    }
+
+    // The pixel itself always has a weight of at least 9/64.
+    sum / weight_sum
+}
+
+// A pixel is a firefly when its luminance stands out from the neighbours around it by more than
+// `FIREFLY_SIGMAS` standard deviations, and is then scaled down to that. Pixels at the very edge
+// of a small image have too few neighbours to tell a firefly from a small bright feature, so
+// they're left alone.
+fn clamp_firefly(colors: &[Color], width: usize, height: usize, index: usize) -> Color {
+    let (i, j) = (index % width, index / width);
+    let mut count = 0;
+    let mut sum = 0.0;
+    let mut sum_squared = 0.0;
+    for qj in j.saturating_sub(2)..=(j + 2).min(height - 1) {
+        for qi in i.saturating_sub(2)..=(i + 2).min(width - 1) {
+            if (qi, qj) != (i, j) {
+                let l = luminance(colors[qj * width + qi]);
+                count += 1;
+                sum += l;
+                sum_squared += l * l;
+            }
+        }
+    }
+    if count < FIREFLY_MIN_NEIGHBOURS {
+        return colors[index];
+    }
+
+    let mean = sum / count as f64;
+    let variance = (sum_squared / count as f64 - mean * mean).max(0.0);
+    let threshold = mean + FIREFLY_SIGMAS * variance.sqrt();
+    let l = luminance(colors[index]);
+    if l > threshold { colors[index] * (threshold / l) } else { colors[index] }
+}
+
+fn finite_or_black(c: Color) -> Color {
+    if c.x.is_finite() && c.y.is_finite() && c.z.is_finite() {
+        c
+    } else {
+        Color::new(0.0, 0.0, 0.0)
+    }
+}
+
+fn luminance(c: Color) -> f64 {
+    0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z
+}
+
+// exp(-|v|^2 / sigma^2): 1 for no difference, going to 0 as the difference outgrows sigma.
+fn gaussian(v: Vec3, sigma: f64) -> f64 {
+    f64::exp(-v.length_squared() / (sigma * sigma))
+}
+
+// Compares colors after squashing them into [0, 1), so a very bright pixel doesn't look
+// unlimitedly different from its neighbours and get left out of the blur.
+fn tone_map(c: Color) -> Color {
+    Color::new(c.x / (1.0 + c.x), c.y / (1.0 + c.y), c.z / (1.0 + c.z))
+}
@@ -0,0 +1,88 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+use crate::aabb::Aabb;
+use crate::hittable::{self, object_id, HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::utils::{random_double, SmallRng};
+use crate::vec3::{dot, Point3, Vec3};
+
+/// A flat disk of `radius` around `center`, facing `normal`.
+///
+/// Its texture coordinates are polar: `u` goes once around the disk and `v` from the center out
+/// to the rim.
+pub struct Disk {
+    center: Point3,
+    radius: f64,
+    basis: Onb,
+    mat: Option<Arc<dyn Material>>,
+    bbox: Aabb,
+}
+
+impl Disk {
+    pub fn new(center: Point3, normal: Vec3, radius: f64, mat: Option<Arc<dyn Material>>) -> Disk {
+        let basis = Onb::new(normal);
+        // Along each axis the rim reaches radius * sin(angle between the axis and the normal)
+        // from the center.
+        let extent = |axis: usize| {
+            let reach = radius * f64::sqrt(f64::max(0.0, 1.0 - basis.w[axis] * basis.w[axis]));
+            Interval::new(center[axis] - reach, center[axis] + reach)
+        };
+        let bbox = Aabb::from_intervals(extent(0), extent(1), extent(2));
+        Disk { center, radius, basis, mat, bbox }
+    }
+}
+
+impl Hittable for Disk {
+    // Intersect the disk's plane the same way `Plane` does, then keep the hit only if it's within
+    // `radius` of the center.
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let normal = self.basis.w;
+        let denom = dot(normal, r.direction);
+        if denom.abs() < 1e-8 {
+            return false;
+        }
+
+        let t = dot(normal, self.center - r.origin) / denom;
+        if !ray_t.surrounds(t) {
+            return false;
+        }
+
+        let p = r.at(t);
+        let offset = p - self.center;
+        let distance = offset.length();
+        if distance > self.radius {
+            return false;
+        }
+
+        rec.t = t;
+        rec.p = Some(p);
+        let phi = f64::atan2(dot(offset, self.basis.v), dot(offset, self.basis.u));
+        rec.u = (phi + PI) / (2.0 * PI);
+        rec.v = distance / self.radius;
+        rec.set_face_normal(r, normal);
+        rec.mat = self.mat.as_ref().map(Arc::clone);
+        rec.object_id = object_id(self);
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        hittable::planar_pdf_value(self, PI * self.radius * self.radius, origin, direction)
+    }
+
+    // A uniformly distributed point on the disk. The area within a distance r of the center
+    // grows with r^2, so the distance is the radius times the square root of a uniform number.
+    fn random(&self, origin: Point3, rng: &mut SmallRng) -> Vec3 {
+        let r = self.radius * f64::sqrt(random_double!(rng));
+        let phi = 2.0 * PI * random_double!(rng);
+        let p = self.center + self.basis.transform(Vec3::new(r * phi.cos(), r * phi.sin(), 0.0));
+        p - origin
+    }
+}
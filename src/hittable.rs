@@ -1,36 +1,38 @@
-use std::rc::Rc;
+use std::sync::Arc;
+use crate::aabb::{self, Aabb};
 use crate::interval::Interval;
 use crate::material::Material;
 use crate::ray::Ray;
+use crate::utils::{random_double, SmallRng};
 use crate::vec3::{dot, Point3, Vec3};
 
-#[derive(Clone)]
-pub(crate) struct HitRecord {
-    pub(crate) p: Option<Point3>,
-    pub(crate) normal: Option<Vec3>,
-    pub(crate) mat : Option<Rc<dyn Material>>,
-    pub(crate) t: f64,
-    pub(crate) front_face: Option<bool>,
+#[derive(Clone, Default)]
+pub struct HitRecord {
+    pub p: Option<Point3>,
+    pub normal: Option<Vec3>,
+    pub mat : Option<Arc<dyn Material>>,
+    pub t: f64,
+    // Surface coordinates of the hit point, for looking up textures.
+    pub u: f64,
+    pub v: f64,
+    pub front_face: Option<bool>,
+    // Which object was hit, from `object_id`, so pixels can be told apart by object. Leaf objects
+    // set it, and objects made of others, like boxes, meshes and instances, put in their own.
+    // 0 if whatever was hit doesn't say: lists and BVH nodes clear it before trying each child.
+    pub object_id: usize,
 }
 
 impl HitRecord {
-    pub(crate) fn new(p: Point3, normal: Vec3, mat: Rc<dyn Material>, t: f64, front_face: bool) -> HitRecord {
+    pub fn new(p: Point3, normal: Vec3, mat: Arc<dyn Material>, t: f64, front_face: bool) -> HitRecord {
         HitRecord {
             p: Some(p),
             normal: Some(normal),
             mat: Some(mat),
             t,
+            u: 0.0,
+            v: 0.0,
             front_face: Some(front_face),
-        }
-    }
-
-    pub(crate) fn default() -> HitRecord {
-        HitRecord {
-            p: None,
-            normal: None,
-            t: 0.0,
-            mat: None,
-            front_face: None,
+            object_id: 0,
         }
     }
 
@@ -47,7 +49,7 @@ impl HitRecord {
     // - If the ray and the normal face in the same direction, the ray is inside the object and vice versa.
     // This can be determined by taking the dot product of the two vectors:
     // - If the dot is positive, the ray is inside the sphere and vice versa.
-    pub(crate) fn set_face_normal(&mut self, r: &Ray, outward_normal: Vec3) {
+    pub fn set_face_normal(&mut self, r: &Ray, outward_normal: Vec3) {
         // Sets the hit record normal vector.
         // NOTE: the parameter `outward_normal` is assumed to have unit length.
         self.front_face = Some(dot(r.direction, outward_normal) < 0.0);
@@ -55,29 +57,78 @@ impl HitRecord {
     }
 }
 
-pub(crate) trait Hittable {
+/// An ID for `object`, for `HitRecord::object_id`: its address, which no other object has as long
+/// as it's alive.
+pub fn object_id<T>(object: &T) -> usize {
+    object as *const T as usize
+}
+
+// `object.hit`, but with `rec.object_id` cleared first, so that an object that doesn't set it
+// isn't credited with the ID of something farther away hit before it. On a miss it's put back.
+pub(crate) fn hit_child(object: &dyn Hittable, r: &Ray, ray_t: Interval, rec: &mut HitRecord)
+                        -> bool {
+    let previous_id = rec.object_id;
+    rec.object_id = 0;
+    let hit = object.hit(r, ray_t, rec);
+    if !hit {
+        rec.object_id = previous_id;
+    }
+    hit
+}
+
+pub trait Hittable: Send + Sync {
     fn hit(&self, r: &Ray, ray_t : Interval, rec: &mut HitRecord) -> bool;
+
+    // The box bounding the object, used to skip it in `BvhNode` when a ray can't possibly hit it.
+    fn bounding_box(&self) -> Aabb;
+
+    // To sample lights directly, an object needs to be able to pick a random direction from
+    // `origin` towards itself, and say how likely it was to pick a given one, as a density per
+    // unit of solid angle (zero for directions that miss it).
+    //
+    // Objects that can't be sampled, like infinite planes and volumes, keep these defaults and
+    // shouldn't be used as lights.
+    fn pdf_value(&self, _origin: Point3, _direction: Vec3) -> f64 {
+        0.0
+    }
+
+    fn random(&self, _origin: Point3, _rng: &mut SmallRng) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
 }
 
-pub(crate) struct HittableList {
-    objects: Vec<Rc<dyn Hittable>>,
+#[derive(Clone)]
+pub struct HittableList {
+    pub objects: Vec<Arc<dyn Hittable>>,
+    bbox: Aabb,
 }
 
 impl HittableList {
-    pub(crate) fn new() -> HittableList {
-        HittableList { objects: Vec::new() }
+    pub fn new() -> HittableList {
+        HittableList { objects: Vec::new(), bbox: aabb::EMPTY }
     }
-    pub(crate) fn add(&mut self, object: Rc<dyn Hittable>) {
+    pub fn add(&mut self, object: Arc<dyn Hittable>) {
+        self.bbox = Aabb::enclosing(&self.bbox, &object.bounding_box());
         self.objects.push(object);
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
 }
+impl Default for HittableList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Hittable for HittableList {
     fn hit(&self, r: &Ray, ray_t : Interval, rec: &mut HitRecord) -> bool {
         let mut hit_anything = false;
         let mut closest_so_far = ray_t.max;
 
         for object in &self.objects {
-            if object.hit(r, Interval::new(ray_t.min, closest_so_far), rec) {
+            if hit_child(object.as_ref(), r, Interval::new(ray_t.min, closest_so_far), rec) {
                 hit_anything = true;
                 closest_so_far = rec.t;
             }
@@ -85,4 +136,42 @@ impl Hittable for HittableList {
 
         hit_anything
     }
-}
\ No newline at end of file
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    // Sampling picks one of the objects at random, so the density is the average of theirs.
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        if self.objects.is_empty() {
+            return 0.0;
+        }
+        let weight = 1.0 / self.objects.len() as f64;
+        self.objects.iter().map(|object| weight * object.pdf_value(origin, direction)).sum()
+    }
+
+    fn random(&self, origin: Point3, rng: &mut SmallRng) -> Vec3 {
+        if self.objects.is_empty() {
+            return Vec3::new(1.0, 0.0, 0.0);
+        }
+        let index = ((random_double!(rng) * self.objects.len() as f64) as usize)
+            .min(self.objects.len() - 1);
+        self.objects[index].random(origin, rng)
+    }
+}
+// https://raytracing.github.io/books/RayTracingTheRestOfYourLife.html#samplinglightsdirectly/gettingthepdfofalight
+// The `pdf_value` of a flat object that's sampled by picking points uniformly over its `area`.
+// A small patch dA of it, seen from `origin` at distance d and tilted by an angle alpha away from
+// the direction, covers a solid angle of dA cos(alpha) / d^2. So the density per unit of area,
+// 1 / A, is a density per unit of solid angle of d^2 / (cos(alpha) A).
+pub(crate) fn planar_pdf_value(object: &dyn Hittable, area: f64, origin: Point3, direction: Vec3)
+                               -> f64 {
+    let mut rec = HitRecord::default();
+    if !object.hit(&Ray::new(origin, direction), Interval::new(0.001, f64::INFINITY), &mut rec) {
+        return 0.0;
+    }
+
+    let distance_squared = rec.t * rec.t * direction.length_squared();
+    let cosine = (dot(direction, rec.normal.unwrap()) / direction.length()).abs();
+    distance_squared / (cosine * area)
+}
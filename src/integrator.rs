@@ -0,0 +1,255 @@
+use crate::background::Background;
+use crate::hittable::{HitRecord, Hittable, HittableList};
+use crate::interval::Interval;
+use crate::material::ScatterRecord;
+use crate::pdf::{HittablePdf, MixturePdf, Pdf};
+use crate::ray::Ray;
+use crate::scene::Scene;
+use crate::utils::SmallRng;
+use crate::vec3::{Color, random_on_hemisphere};
+
+// An integrator works out the color seen along a camera ray: what the camera's pixels are made
+// of. The path tracer is the one that makes pictures; the others show one property of the first
+// surface each ray hits, which helps with debugging a scene.
+//
+// Paths are followed with a loop rather than recursion, carrying the fraction of light that
+// still makes it back to the camera (the throughput) from one bounce to the next, so however big
+// `max_depth` gets it can't overflow the stack.
+pub trait Integrator: Send + Sync {
+    // The color seen along `r`, following it through `world` for at most `max_depth` bounces. Rays
+    // that miss everything see `background`.
+    fn ray_color(&self, r: &Ray, world: &dyn Hittable, background: &Background, max_depth: i32,
+                 rng: &mut SmallRng) -> Color;
+}
+
+/// Names of the integrators, for `from_name`.
+pub const INTEGRATORS: [&str; 6] = ["path", "diffuse", "normals", "albedo", "depth", "hit_count"];
+
+/// Builds the integrator called `name` to render `scene` with, or `None` if there's no such
+/// integrator.
+pub fn from_name(name: &str, scene: &Scene) -> Option<Box<dyn Integrator>> {
+    Some(match name {
+        "path" => Box::new(PathTracer::new(scene.lights.clone())),
+        "diffuse" => Box::new(Diffuse),
+        "normals" => Box::new(Normals),
+        "albedo" => Box::new(Albedo),
+        "depth" => {
+            // Whatever the camera is looking at ends up mid-gray.
+            let focus = (scene.camera.lookfrom - scene.camera.lookat).length();
+            Box::new(Depth::new(2.0 * focus))
+        }
+        "hit_count" => Box::new(HitCount),
+        _ => return None,
+    })
+}
+
+// 0.001 - fix shadow acne: bug associated with floating point rounding errors on object
+// intersections
+pub(crate) const SHADOW_ACNE: Interval = Interval { min: 0.001, max: f64::INFINITY };
+
+// Light sources are just materials that emit light, so a ray that hits one picks up its
+// emitted color on top of whatever it gathers by scattering further. A ray that escapes the
+// scene takes on the background, which is the only light in scenes without any emitters.
+//
+// Small lights are rarely hit by chance, which makes for a lot of noise, so diffuse bounces
+// aim half their rays straight at `lights` (next event estimation). Whichever way a
+// direction was picked, its contribution is weighed by the material's scattering density
+// over the density it was drawn with, which keeps the estimate unbiased.
+// https://raytracing.github.io/books/RayTracingTheRestOfYourLife.html#mixturedensities
+pub struct PathTracer {
+    lights: HittableList,
+}
+
+impl PathTracer {
+    pub fn new(lights: HittableList) -> Self {
+        Self { lights }
+    }
+}
+
+impl Integrator for PathTracer {
+    fn ray_color(&self, r: &Ray, world: &dyn Hittable, background: &Background, max_depth: i32,
+                 rng: &mut SmallRng) -> Color {
+        let mut color = Color::new(0.0, 0.0, 0.0);
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut ray = *r;
+
+        // If we've exceeded the ray bounce limit, no more light is gathered.
+        for _ in 0..max_depth {
+            let mut rec = HitRecord::default();
+            if !world.hit(&ray, SHADOW_ACNE, &mut rec) {
+                color += throughput * background.value(&ray);
+                break;
+            }
+
+            let Some(mat) = rec.mat.as_ref() else { break };
+            let p = rec.p.unwrap();
+            color += throughput * mat.emitted(rec.u, rec.v, p);
+
+            let mut srec = ScatterRecord::default();
+            if !mat.scatter(&ray, &rec, &mut srec, rng) {
+                break;
+            }
+
+            // Specular bounces go exactly one way, there's nothing to sample.
+            if let Some(scattered) = srec.skip_pdf_ray {
+                throughput *= srec.attenuation;
+                ray = scattered;
+                continue;
+            }
+
+            let surface_pdf = srec.pdf.expect("Unable to scatter without a pdf");
+            let light_pdf = HittablePdf::new(&self.lights, p);
+            let mixture_pdf = MixturePdf::new(&light_pdf, surface_pdf.as_ref());
+            let pdf: &dyn Pdf = if self.lights.is_empty() {
+                surface_pdf.as_ref()
+            } else {
+                &mixture_pdf
+            };
+
+            let scattered = Ray::with_time(p, pdf.generate(rng), ray.time);
+            let pdf_value = pdf.value(scattered.direction);
+            if pdf_value <= 0.0 {
+                break;
+            }
+            let scattering_pdf = mat.scattering_pdf(&ray, &rec, &scattered);
+
+            throughput = throughput * srec.attenuation * scattering_pdf / pdf_value;
+            ray = scattered;
+        }
+
+        color
+    }
+}
+
+// Diffuse objects that don't emit their own light merely take on the color of their
+// surroundings, but they do modulate with their own intrinsic color.
+//
+// Light that reflects off a diffuse surface has its direction randomized, so, if we send
+// three rays into a crack between two diffuse surfaces they will each have different random
+// behavior.
+//
+// They might also be absorbed rather than reflected.
+//
+// An algorithm that randomizes direction will produce surfaces that look matte.
+// The simplest diffuse material is one in which it has an equal chance of reflecting light
+// in any direction. This ignores materials altogether and makes everything a gray that
+// absorbs half the light at every bounce.
+// https://raytracing.github.io/books/RayTracingInOneWeekend.html#diffusematerials
+pub struct Diffuse;
+
+impl Integrator for Diffuse {
+    fn ray_color(&self, r: &Ray, world: &dyn Hittable, background: &Background, max_depth: i32,
+                 rng: &mut SmallRng) -> Color {
+        let mut attenuation = 1.0;
+        let mut ray = *r;
+
+        for _ in 0..max_depth {
+            let mut rec = HitRecord::default();
+            if !world.hit(&ray, SHADOW_ACNE, &mut rec) {
+                return attenuation * background.value(&ray);
+            }
+            let direction = random_on_hemisphere(rng, rec.normal.unwrap());
+            ray = Ray::with_time(rec.p.unwrap(), direction, ray.time);
+            attenuation *= 0.5;
+        }
+
+        Color::new(0.0, 0.0, 0.0)
+    }
+}
+
+// Surface normals, mapped from [-1, 1] to [0, 1] per component. Since the normals point against
+// the ray, a surface's inside looks different from its outside.
+// https://raytracing.github.io/books/RayTracingInOneWeekend.html#surfacenormalsandmultipleobjects
+pub struct Normals;
+
+impl Integrator for Normals {
+    fn ray_color(&self, r: &Ray, world: &dyn Hittable, background: &Background, _max_depth: i32,
+                 _rng: &mut SmallRng) -> Color {
+        let mut rec = HitRecord::default();
+        if world.hit(r, Interval::new(0.0, f64::INFINITY), &mut rec) {
+            return 0.5 * (rec.normal.unwrap() + Color::new(1.0, 1.0, 1.0));
+        }
+
+        background.value(r)
+    }
+}
+
+// The color of the first surface hit, without any lighting: the attenuation it scatters with,
+// or for lights, the color they emit.
+pub struct Albedo;
+
+impl Integrator for Albedo {
+    fn ray_color(&self, r: &Ray, world: &dyn Hittable, background: &Background, _max_depth: i32,
+                 rng: &mut SmallRng) -> Color {
+        let mut rec = HitRecord::default();
+        if !world.hit(r, SHADOW_ACNE, &mut rec) {
+            return background.value(r);
+        }
+        surface_albedo(r, &rec, rng)
+    }
+}
+
+// The color of the surface `r` hit, as recorded in `rec`.
+pub(crate) fn surface_albedo(r: &Ray, rec: &HitRecord, rng: &mut SmallRng) -> Color {
+    let Some(mat) = rec.mat.as_ref() else { return Color::new(0.0, 0.0, 0.0) };
+
+    let mut srec = ScatterRecord::default();
+    if mat.scatter(r, rec, &mut srec, rng) {
+        srec.attenuation
+    } else {
+        mat.emitted(rec.u, rec.v, rec.p.unwrap())
+    }
+}
+
+// The distance to the first surface hit, in shades of gray: white right at the camera, fading
+// to black at `max_distance` and beyond. Rays that hit nothing are black too.
+pub struct Depth {
+    max_distance: f64,
+}
+
+impl Depth {
+    pub fn new(max_distance: f64) -> Self {
+        Self { max_distance }
+    }
+}
+
+impl Integrator for Depth {
+    fn ray_color(&self, r: &Ray, world: &dyn Hittable, _background: &Background, _max_depth: i32,
+                 _rng: &mut SmallRng) -> Color {
+        let mut rec = HitRecord::default();
+        if !world.hit(r, SHADOW_ACNE, &mut rec) {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+
+        // Camera rays aren't unit length, so `t` isn't a distance by itself.
+        let distance = rec.t * r.direction.length();
+        let gray = 1.0 - Interval::new(0.0, 1.0).clamp(distance / self.max_distance);
+        Color::new(gray, gray, gray)
+    }
+}
+
+// How many surfaces the camera ray passes through, ignoring materials, up to `max_depth`. Every
+// surface halves the remaining distance to white, so nothing is black, one surface is mid-gray,
+// and going through a sphere's front and back is 3/4 gray. Handy for seeing hidden geometry and
+// how much of it each pixel has to test.
+pub struct HitCount;
+
+impl Integrator for HitCount {
+    fn ray_color(&self, r: &Ray, world: &dyn Hittable, _background: &Background, max_depth: i32,
+                 _rng: &mut SmallRng) -> Color {
+        let mut hits = 0;
+        let mut ray_t = SHADOW_ACNE;
+
+        while hits < max_depth {
+            let mut rec = HitRecord::default();
+            if !world.hit(r, ray_t, &mut rec) {
+                break;
+            }
+            hits += 1;
+            ray_t.min = rec.t + SHADOW_ACNE.min;
+        }
+
+        let gray = 1.0 - 0.5f64.powi(hits);
+        Color::new(gray, gray, gray)
+    }
+}
@@ -1,28 +1,47 @@
-pub(crate) struct Interval {
-    pub(crate) min: f64,
-    pub(crate) max: f64,
+#[derive(Clone, Copy, Debug)]
+pub struct Interval {
+    pub min: f64,
+    pub max: f64,
 }
 
 impl Interval {
-    pub(crate) fn new(min: f64, max: f64) -> Self {
+    pub fn new(min: f64, max: f64) -> Self {
         Interval { min, max }
     }
 
-    pub(crate) fn default() -> Self {
-        Interval { min: f64::INFINITY, max: f64::NEG_INFINITY }
+    // Create the interval tightly enclosing the two input intervals.
+    pub fn enclosing(a: Interval, b: Interval) -> Self {
+        Interval { min: f64::min(a.min, b.min), max: f64::max(a.max, b.max) }
     }
 
-    pub(crate) fn contains(&self, x: f64) -> bool {
+    pub fn contains(&self, x: f64) -> bool {
         self.min <= x && x <= self.max
     }
 
-    pub(crate) fn surrounds(&self, x: f64) -> bool {
+    pub fn surrounds(&self, x: f64) -> bool {
         self.min < x && x < self.max
     }
 
-    pub(crate) fn clamp(&self, x: f64) -> f64 {
+    pub fn clamp(&self, x: f64) -> f64 {
         f64::max(f64::min(x, self.max), self.min)
     }
+
+    pub fn size(&self) -> f64 {
+        self.max - self.min
+    }
+
+    // Pads the interval by `delta` in total, half on each side.
+    pub fn expand(&self, delta: f64) -> Self {
+        let padding = delta / 2.0;
+        Interval { min: self.min - padding, max: self.max + padding }
+    }
+}
+
+// The default interval is empty.
+impl Default for Interval {
+    fn default() -> Self {
+        EMPTY
+    }
 }
 
 pub const EMPTY: Interval = Interval { min: f64::INFINITY, max: f64::NEG_INFINITY };
@@ -0,0 +1,55 @@
+//! A ray tracer following Peter Shirley's _Ray Tracing in One Weekend_ series.
+//!
+//! The crate is organized the same way as the renderer:
+//! - math: [`vec3`], [`ray`] and [`interval`],
+//! - geometry: the [`hittable`] trait, [`sphere`], [`triangle`] and [`mesh`], the flat
+//!   [`plane`], [`quad`] and [`disk`], instances placed with a [`transform`], volumes of
+//!   [`constant_medium`], and the [`aabb`]/[`bvh`] acceleration structure,
+//! - [`material`]s describing how rays scatter off a surface, colored by [`texture`]s such as
+//!   images or Perlin noise, and the [`pdf`]s bounces are importance sampled with,
+//! - the [`camera`], which renders a world in front of a [`background`] into an [`output`]
+//!   framebuffer, with an [`integrator`] working out the color seen along each ray, and
+//!   [`Aov`] images of what the rays hit, which can [`denoise()`] the render,
+//! - [`scene`] files and the scenes from the book.
+//!
+//! The types most programs need are also available from the crate root.
+
+pub mod vec3;
+pub mod ray;
+pub mod interval;
+pub(crate) mod utils;
+pub(crate) mod onb;
+pub mod hittable;
+pub mod sphere;
+pub mod triangle;
+pub mod plane;
+pub mod quad;
+pub mod disk;
+pub mod mesh;
+pub mod transform;
+pub mod constant_medium;
+pub mod aabb;
+pub mod bvh;
+pub(crate) mod perlin;
+pub mod texture;
+pub mod material;
+pub mod pdf;
+pub mod background;
+pub mod camera;
+pub mod integrator;
+pub(crate) mod aov;
+pub(crate) mod denoise;
+pub mod output;
+pub mod scene;
+
+pub use aov::{Aov, AovBuffers, AOVS};
+pub use background::Background;
+pub use camera::Camera;
+pub use denoise::denoise;
+pub use hittable::{HitRecord, Hittable, HittableList};
+pub use integrator::Integrator;
+pub use output::Framebuffer;
+pub use ray::Ray;
+pub use scene::Scene;
+pub use utils::{new_rng, SmallRng};
+pub use vec3::{Color, Point3, Vec3};
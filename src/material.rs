@@ -1,50 +1,219 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
 use crate::hittable::HitRecord;
+use crate::pdf::{CosinePdf, Pdf, SpherePdf};
 use crate::ray::Ray;
-use crate::vec3::{Color, random_unit_vector, reflect, unit_vector, Vec3};
+use crate::texture::{SolidColor, Texture};
+use crate::utils::{random_double, SmallRng};
+use crate::vec3::{Color, dot, Point3, random_unit_vector, reflect, refract, unit_vector};
+
+// https://raytracing.github.io/books/RayTracingTheRestOfYourLife.html#cleaninguppdfmanagement
+// How a material scatters a ray. There are two kinds of scattering:
+//    1. Diffuse: light goes off in all directions, following a distribution the material gives
+//       as a `pdf`. The integrator draws the scattered direction from it, or from a mix of it
+//       and the lights, and weighs the result by `scattering_pdf`.
+//    2. Specular: light goes off in one particular direction, like off a mirror or through
+//       glass. No distribution can be mixed with that, so the material picks the ray itself.
+#[derive(Default)]
+pub struct ScatterRecord {
+    // How much of each color component survives the bounce.
+    pub attenuation: Color,
+    pub pdf: Option<Box<dyn Pdf>>,
+    // The ray to follow for specular scattering, skipping the pdf.
+    pub skip_pdf_ray: Option<Ray>,
+}
 
 // Material needs to do two things:
-//    1. Produce a scattered ray (or say it absorbed the incident ray).
+//    1. Say how the incident ray scatters (or say it's absorbed).
 //    2. If it scattered, say how much the ray should be attenuated.
-pub(crate) trait Material {
-    fn new(a: Color) -> Self where Self: Sized;
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord, attenuation: &mut Color, scattered: &mut Ray)
-               -> bool;
+// Materials that give off light also say how much they emit.
+pub trait Material: Send + Sync {
+    // Lights only emit, so not scattering is the default.
+    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord, _srec: &mut ScatterRecord,
+               _rng: &mut SmallRng) -> bool {
+        false
+    }
+
+    // The density of diffuse scattering towards `scattered`, per unit of solid angle.
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
+        0.0
+    }
+
+    // Most materials don't emit any light.
+    fn emitted(&self, _u: f64, _v: f64, _p: Point3) -> Color {
+        Color::new(0.0, 0.0, 0.0)
+    }
 }
 
-pub(crate) struct Lambertian {
-    albedo: Color,
+pub struct Lambertian {
+    tex: Arc<dyn Texture>,
 }
 
+impl Lambertian {
+    pub fn new(a: Color) -> Self {
+        Self::from_texture(Arc::new(SolidColor::new(a)))
+    }
+
+    pub fn from_texture(tex: Arc<dyn Texture>) -> Self {
+        Self { tex }
+    }
+}
+
+// An ideal diffuse surface scatters light with a density proportional to cos(theta), where
+// theta is the angle from the normal: cos(theta) / pi once normalized over the hemisphere.
 impl Material for Lambertian {
-    fn new(a: Color) -> Self {
-        Self { albedo: a }
+    fn scatter(&self, _r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord,
+               _rng: &mut SmallRng) -> bool {
+        srec.attenuation = self.tex.value(rec.u, rec.v, rec.p.unwrap());
+        srec.pdf = Some(Box::new(CosinePdf::new(rec.normal.unwrap())));
+        srec.skip_pdf_ray = None;
+        true
+    }
+
+    fn scattering_pdf(&self, _r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        let cos_theta = dot(rec.normal.unwrap(), unit_vector(scattered.direction));
+        f64::max(0.0, cos_theta / PI)
+    }
+}
+
+pub struct Metal {
+    tex: Arc<dyn Texture>,
+    // Radius of the sphere the reflected ray's endpoint is randomly moved within. The bigger the
+    // sphere, the fuzzier the reflections will be. Zero gives a perfect mirror.
+    fuzz: f64,
+}
+
+impl Metal {
+    pub fn new(a: Color, f: f64) -> Self {
+        Self::from_texture(Arc::new(SolidColor::new(a)), f)
+    }
+
+    pub fn from_texture(tex: Arc<dyn Texture>, f: f64) -> Self {
+        Self { tex, fuzz: f64::min(f, 1.0) }
     }
+}
 
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord, attenuation: &mut Color, scattered: &mut Ray) -> bool {
+impl Material for Metal {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord,
+               rng: &mut SmallRng) -> bool {
         let normal = rec.normal.unwrap();
-        let mut scatter_direction: Vec3 = normal + random_unit_vector();
-        if scatter_direction.near_zero() {
-            scatter_direction = normal;
-        }
-        *scattered = Ray::new(rec.p.unwrap(), scatter_direction);
-        *attenuation = self.albedo;
+        let reflected = reflect(unit_vector(r_in.direction), normal);
+        let scattered = Ray::with_time(rec.p.unwrap(), reflected + self.fuzz * random_unit_vector(rng),
+                                       r_in.time);
+        srec.attenuation = self.tex.value(rec.u, rec.v, rec.p.unwrap());
+        srec.pdf = None;
+        srec.skip_pdf_ray = Some(scattered);
+        // For big spheres or grazing rays, the fuzzed ray may scatter below the surface. We just
+        // have the surface absorb those.
+        dot(scattered.direction, normal) > 0.0
+    }
+}
+
+// Clear materials such as water, glass, and diamond are dielectrics. When a light ray hits them,
+// it splits into a reflected ray and a refracted (transmitted) ray. We handle that by randomly
+// choosing between reflection and refraction, only generating one scattered ray per interaction.
+//
+// A glass surface absorbs nothing, so the attenuation is always 1.
+pub struct Dielectric {
+    // Refractive index in vacuum or air, or the ratio of the material's refractive index over
+    // the refractive index of the enclosing media.
+    ir: f64,
+}
+
+impl Dielectric {
+    pub fn new(index_of_refraction: f64) -> Self {
+        Self { ir: index_of_refraction }
+    }
+
+    // Real glass has reflectivity that varies with angle: look at a window at a steep angle and
+    // it becomes a mirror. Christophe Schlick's polynomial approximation is cheap and
+    // surprisingly accurate:
+    //     R(theta) = R0 + (1 - R0)(1 - cos(theta))^5, where R0 = ((1 - eta) / (1 + eta))^2
+    fn reflectance(cosine: f64, ref_idx: f64) -> f64 {
+        let r0 = ((1.0 - ref_idx) / (1.0 + ref_idx)).powi(2);
+        r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
+    }
+}
+
+impl Material for Dielectric {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord,
+               rng: &mut SmallRng) -> bool {
+        srec.attenuation = Color::new(1.0, 1.0, 1.0);
+        srec.pdf = None;
+        // Entering the surface we go from air (1.0) to the material, leaving we go the other way.
+        let refraction_ratio = if rec.front_face.unwrap() { 1.0 / self.ir } else { self.ir };
+
+        let normal = rec.normal.unwrap();
+        let unit_direction = unit_vector(r_in.direction);
+        let cos_theta = f64::min(dot(-unit_direction, normal), 1.0);
+        let sin_theta = f64::sqrt(1.0 - cos_theta * cos_theta);
+
+        // Snell's law: sin(theta') = eta / eta' * sin(theta). When the ray is inside the
+        // material with the higher refractive index, the right hand side can exceed 1, in which
+        // case there is no solution and the ray must be reflected (total internal reflection).
+        let cannot_refract = refraction_ratio * sin_theta > 1.0;
+        let direction = if cannot_refract
+            || Self::reflectance(cos_theta, refraction_ratio) > random_double!(rng) {
+            reflect(unit_direction, normal)
+        } else {
+            refract(unit_direction, normal, refraction_ratio)
+        };
+
+        srec.skip_pdf_ray = Some(Ray::with_time(rec.p.unwrap(), direction, r_in.time));
         true
     }
 }
 
-pub(crate) struct Metal {
-    albedo: Color,
+// https://raytracing.github.io/books/RayTracingTheNextWeek.html#lights/emissivematerials
+// A light source that emits `emit` evenly in every direction, and doesn't reflect any light.
+// Components brighter than 1 are fine, and needed for a small light to light up a room.
+pub struct DiffuseLight {
+    tex: Arc<dyn Texture>,
 }
 
-impl Material for Metal {
-    fn new(a: Color) -> Self {
-        Self { albedo: a }
+impl DiffuseLight {
+    pub fn new(emit: Color) -> Self {
+        Self::from_texture(Arc::new(SolidColor::new(emit)))
     }
 
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord, attenuation: &mut Color, scattered: &mut Ray) -> bool {
-        let reflected = reflect(unit_vector(r_in.direction), rec.normal.unwrap());
-        *scattered = Ray::new(rec.p.unwrap(), reflected);
-        *attenuation = self.albedo;
+    pub fn from_texture(tex: Arc<dyn Texture>) -> Self {
+        Self { tex }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn emitted(&self, u: f64, v: f64, p: Point3) -> Color {
+        self.tex.value(u, v, p)
+    }
+}
+
+// https://raytracing.github.io/books/RayTracingTheNextWeek.html#volumes/constantdensitymediums
+// The phase function of a volume that scatters light equally in every direction, as fog and smoke
+// roughly do. It's the volume's counterpart to `Lambertian`, tinted by its albedo texture.
+pub struct Isotropic {
+    tex: Arc<dyn Texture>,
+}
+
+impl Isotropic {
+    pub fn new(albedo: Color) -> Self {
+        Self::from_texture(Arc::new(SolidColor::new(albedo)))
+    }
+
+    pub fn from_texture(tex: Arc<dyn Texture>) -> Self {
+        Self { tex }
+    }
+}
+
+impl Material for Isotropic {
+    fn scatter(&self, _r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord,
+               _rng: &mut SmallRng) -> bool {
+        srec.attenuation = self.tex.value(rec.u, rec.v, rec.p.unwrap());
+        srec.pdf = Some(Box::new(SpherePdf));
+        srec.skip_pdf_ray = None;
         true
     }
-}
\ No newline at end of file
+
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+}
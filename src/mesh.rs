@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::{fmt, fs, io};
+use crate::aabb::Aabb;
+use crate::bvh::BvhNode;
+use crate::hittable::{object_id, HitRecord, Hittable, HittableList};
+use crate::interval::Interval;
+use crate::material::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+use crate::ray::Ray;
+use crate::texture::ImageTexture;
+use crate::triangle::Triangle;
+use crate::utils::SmallRng;
+use crate::vec3::{Color, Point3, Vec3};
+
+/// A triangle mesh, kept in its own bounding volume hierarchy.
+///
+/// A mesh is a single `Hittable`, so it goes into a `HittableList` like any other object, and
+/// since it's shared through an `Arc` the same mesh can be placed in a scene any number of times.
+pub struct Mesh {
+    bvh: BvhNode,
+}
+
+#[derive(Debug)]
+pub enum MeshError {
+    Io { path: PathBuf, err: io::Error },
+    Parse { path: PathBuf, line: usize, message: String },
+    Empty { path: PathBuf },
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeshError::Io { path, err } => write!(f, "unable to read {}: {err}", path.display()),
+            MeshError::Parse { path, line, message } =>
+                write!(f, "{}:{line}: {message}", path.display()),
+            MeshError::Empty { path } => write!(f, "{} has no faces", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for MeshError {}
+
+impl Mesh {
+    /// Builds a mesh out of `triangles`, which must not be empty.
+    pub fn new(triangles: Vec<Triangle>) -> Mesh {
+        let mut list = HittableList::new();
+        for triangle in triangles {
+            list.add(Arc::new(triangle));
+        }
+        Mesh { bvh: BvhNode::new(list) }
+    }
+
+    /// Loads a Wavefront OBJ file, along with the materials in any `.mtl` files it refers to.
+    /// Faces that come before any `usemtl` get `default_material`.
+    ///
+    /// Polygons are split into triangle fans. Vertex normals and texture coordinates are used
+    /// when every corner of a face has them. Statements we don't render, like groups, smoothing
+    /// groups, lines and points, are skipped.
+    pub fn load(file_path: &str, default_material: Option<Arc<dyn Material>>)
+                -> Result<Mesh, MeshError> {
+        let path = Path::new(file_path);
+        let contents = read(path)?;
+
+        let mut positions: Vec<Point3> = Vec::new();
+        let mut uvs: Vec<(f64, f64)> = Vec::new();
+        let mut normals: Vec<Vec3> = Vec::new();
+        let mut materials: HashMap<String, Arc<dyn Material>> = HashMap::new();
+        let mut material = default_material;
+        let mut triangles = Vec::new();
+
+        for (index, line) in contents.lines().enumerate() {
+            let error = |message: String| MeshError::Parse { path: path.to_path_buf(),
+                                                             line: index + 1, message };
+            let mut tokens = statement(line);
+            let Some(keyword) = tokens.next() else { continue };
+            let args: Vec<&str> = tokens.collect();
+
+            match keyword {
+                "v" => positions.push(parse_vec3(&args).map_err(error)?),
+                "vn" => normals.push(parse_vec3(&args).map_err(error)?),
+                "vt" => {
+                    let uv = parse_floats(&args, 1).map_err(error)?;
+                    uvs.push((uv[0], uv.get(1).copied().unwrap_or(0.0)));
+                }
+                "f" => {
+                    if args.len() < 3 {
+                        return Err(error("a face needs at least three vertices".to_string()));
+                    }
+                    let corners = args.iter()
+                        .map(|corner| parse_corner(corner, positions.len(), uvs.len(),
+                                                   normals.len()))
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(error)?;
+
+                    // Fan out from the first corner: (0, 1, 2), (0, 2, 3), ...
+                    for k in 1..corners.len() - 1 {
+                        let face = [corners[0], corners[k], corners[k + 1]];
+                        let mut triangle = Triangle::new(positions[face[0].0], positions[face[1].0],
+                                                         positions[face[2].0], material.clone());
+                        if let [Some(a), Some(b), Some(c)] = face.map(|corner| corner.2) {
+                            triangle = triangle.with_normals([normals[a], normals[b], normals[c]]);
+                        }
+                        if let [Some(a), Some(b), Some(c)] = face.map(|corner| corner.1) {
+                            triangle = triangle.with_uvs([uvs[a], uvs[b], uvs[c]]);
+                        }
+                        triangles.push(triangle);
+                    }
+                }
+                "mtllib" => {
+                    let directory = path.parent().unwrap_or(Path::new(""));
+                    for library in args {
+                        materials.extend(load_mtl(&directory.join(library))?);
+                    }
+                }
+                "usemtl" => {
+                    let name = args.join(" ");
+                    material = Some(materials.get(&name).cloned()
+                        .ok_or_else(|| error(format!("unknown material `{name}`")))?);
+                }
+                _ => {}
+            }
+        }
+
+        if triangles.is_empty() {
+            return Err(MeshError::Empty { path: path.to_path_buf() });
+        }
+        Ok(Mesh::new(triangles))
+    }
+}
+
+impl Hittable for Mesh {
+    // The mesh is one object, however many triangles it has.
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        if !self.bvh.hit(r, ray_t, rec) {
+            return false;
+        }
+        rec.object_id = object_id(self);
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bvh.bounding_box()
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        self.bvh.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: Point3, rng: &mut SmallRng) -> Vec3 {
+        self.bvh.random(origin, rng)
+    }
+}
+
+// The properties of one `newmtl` entry that we know how to render.
+struct MtlMaterial {
+    diffuse: Color,
+    // An image to use in place of `diffuse`, from `map_Kd`.
+    diffuse_map: Option<PathBuf>,
+    specular: Color,
+    emissive: Color,
+    specular_exponent: f64,
+    index_of_refraction: f64,
+    dissolve: f64,
+    illum: i32,
+}
+
+impl MtlMaterial {
+    // MTL describes materials for a Phong-style shading model, so this is only an approximation:
+    // - materials with an emissive color `Ke` become lights,
+    // - transparent materials (`d` below 1, or an illumination model with refraction) become
+    //   glass with index of refraction `Ni`,
+    // - illumination models with ray traced reflections (3 and 5) become metal tinted by `Ks`,
+    //   with a fuzz taken from the specular exponent `Ns`, using the usual conversion from a
+    //   Phong exponent to a roughness: sqrt(2 / (Ns + 2)),
+    // - everything else is Lambertian with albedo `Kd`, or the image `map_Kd`.
+    fn build(&self) -> Result<Arc<dyn Material>, MeshError> {
+        Ok(if !self.emissive.near_zero() {
+            Arc::new(DiffuseLight::new(self.emissive))
+        } else if self.dissolve < 1.0 || matches!(self.illum, 4 | 6 | 7 | 9) {
+            Arc::new(Dielectric::new(self.index_of_refraction))
+        } else if matches!(self.illum, 3 | 5) {
+            let fuzz = f64::sqrt(2.0 / (self.specular_exponent + 2.0));
+            Arc::new(Metal::new(self.specular, fuzz))
+        } else if let Some(path) = &self.diffuse_map {
+            let texture = ImageTexture::load(&path.to_string_lossy())
+                .map_err(|err| MeshError::Io { path: path.clone(), err })?;
+            Arc::new(Lambertian::from_texture(Arc::new(texture)))
+        } else {
+            Arc::new(Lambertian::new(self.diffuse))
+        })
+    }
+}
+
+impl Default for MtlMaterial {
+    fn default() -> Self {
+        MtlMaterial {
+            diffuse: Color::new(0.8, 0.8, 0.8),
+            diffuse_map: None,
+            specular: Color::new(1.0, 1.0, 1.0),
+            emissive: Color::new(0.0, 0.0, 0.0),
+            specular_exponent: 0.0,
+            index_of_refraction: 1.5,
+            dissolve: 1.0,
+            illum: 2,
+        }
+    }
+}
+
+fn load_mtl(path: &Path) -> Result<HashMap<String, Arc<dyn Material>>, MeshError> {
+    let contents = read(path)?;
+
+    let mut parsed: Vec<(String, MtlMaterial)> = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let error = |message: String| MeshError::Parse { path: path.to_path_buf(),
+                                                         line: index + 1, message };
+        let mut tokens = statement(line);
+        let Some(keyword) = tokens.next() else { continue };
+        let args: Vec<&str> = tokens.collect();
+
+        if keyword == "newmtl" {
+            parsed.push((args.join(" "), MtlMaterial::default()));
+            continue;
+        }
+        let Some((_, material)) = parsed.last_mut() else {
+            return Err(error(format!("`{keyword}` before any `newmtl`")));
+        };
+        match keyword {
+            "Kd" => material.diffuse = parse_vec3(&args).map_err(error)?,
+            "Ks" => material.specular = parse_vec3(&args).map_err(error)?,
+            "Ke" => material.emissive = parse_vec3(&args).map_err(error)?,
+            "Ns" => material.specular_exponent = parse_floats(&args, 1).map_err(error)?[0],
+            "Ni" => material.index_of_refraction = parse_floats(&args, 1).map_err(error)?[0],
+            "d" => material.dissolve = parse_floats(&args, 1).map_err(error)?[0],
+            "Tr" => material.dissolve = 1.0 - parse_floats(&args, 1).map_err(error)?[0],
+            // Texture map options like `-s` or `-clamp` come first; the file name is last.
+            "map_Kd" => {
+                let file_name = args.last()
+                    .ok_or_else(|| error("`map_Kd` expects a file name".to_string()))?;
+                let directory = path.parent().unwrap_or(Path::new(""));
+                material.diffuse_map = Some(directory.join(file_name));
+            }
+            "illum" => {
+                material.illum = args.first().and_then(|illum| illum.parse().ok())
+                    .ok_or_else(|| error("`illum` expects an integer".to_string()))?;
+            }
+            _ => {}
+        }
+    }
+
+    parsed.into_iter().map(|(name, material)| Ok((name, material.build()?))).collect()
+}
+
+fn read(path: &Path) -> Result<String, MeshError> {
+    fs::read_to_string(path).map_err(|err| MeshError::Io { path: path.to_path_buf(), err })
+}
+
+// The whitespace separated tokens of a line, without any trailing `#` comment.
+fn statement(line: &str) -> std::str::SplitWhitespace<'_> {
+    line.split('#').next().unwrap_or("").split_whitespace()
+}
+
+fn parse_floats(args: &[&str], min_count: usize) -> Result<Vec<f64>, String> {
+    if args.len() < min_count {
+        return Err(format!("expected at least {min_count} numbers"));
+    }
+    args.iter()
+        .map(|arg| arg.parse::<f64>().map_err(|_| format!("invalid number `{arg}`")))
+        .collect()
+}
+
+fn parse_vec3(args: &[&str]) -> Result<Vec3, String> {
+    let v = parse_floats(args, 3)?;
+    Ok(Vec3::new(v[0], v[1], v[2]))
+}
+
+// A face corner is `v`, `v/vt`, `v//vn` or `v/vt/vn`, each a 1-based index into the vertex data
+// read so far, or a negative index counting back from the end of it. Returns 0-based indices.
+fn parse_corner(corner: &str, positions: usize, uvs: usize, normals: usize)
+                -> Result<(usize, Option<usize>, Option<usize>), String> {
+    let mut parts = corner.split('/');
+    let resolve = |index: Option<&str>, count: usize| -> Result<Option<usize>, String> {
+        let Some(index) = index.filter(|index| !index.is_empty()) else { return Ok(None) };
+        let resolved = match index.parse::<i64>() {
+            Ok(i) if i > 0 && i as usize <= count => i as usize - 1,
+            Ok(i) if i < 0 && i.unsigned_abs() as usize <= count => count - i.unsigned_abs() as usize,
+            _ => return Err(format!("invalid vertex index `{index}` in `{corner}`")),
+        };
+        Ok(Some(resolved))
+    };
+
+    let position = resolve(parts.next(), positions)?
+        .ok_or_else(|| format!("missing vertex index in `{corner}`"))?;
+    let uv = resolve(parts.next(), uvs)?;
+    let normal = resolve(parts.next(), normals)?;
+    Ok((position, uv, normal))
+}
@@ -0,0 +1,27 @@
+use crate::vec3::{cross, unit_vector, Vec3};
+
+/// An orthonormal basis: three mutually perpendicular unit vectors, with `w` along the vector
+/// it was built from. Used to turn coordinates relative to a surface into world coordinates.
+#[derive(Clone, Copy, Debug)]
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    // Any vector not parallel to `n` gives us a second axis through the cross product, so pick
+    // whichever of the x and y axes is further from it.
+    pub fn new(n: Vec3) -> Self {
+        let w = unit_vector(n);
+        let a = if w.x.abs() > 0.9 { Vec3::new(0.0, 1.0, 0.0) } else { Vec3::new(1.0, 0.0, 0.0) };
+        let v = unit_vector(cross(w, a));
+        let u = cross(w, v);
+        Onb { u, v, w }
+    }
+
+    // Converts coordinates relative to this basis to world coordinates.
+    pub fn transform(&self, a: Vec3) -> Vec3 {
+        (a.x * self.u) + (a.y * self.v) + (a.z * self.w)
+    }
+}
@@ -0,0 +1,468 @@
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use crate::interval::Interval;
+use crate::vec3::Color;
+
+/// An in-memory image that renderers fill in pixel by pixel and then save in one go.
+///
+/// Pixels are stored row by row starting from the top left, the same order we render them in.
+/// They hold linear, unclamped radiance so nothing is lost before the image is saved. The 8-bit
+/// formats clamp each component to [0, 1] (gamma encoding it first if asked to), while the HDR
+/// formats write the floating point values as they are.
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+    // Whether 8-bit output should be gamma encoded. The early chapters write their colors
+    // straight to the file, so this is off unless the renderer turns it on.
+    pub gamma_correction: bool,
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self::from_pixels(width, height, vec![Color::default(); width * height])
+    }
+
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<Color>) -> Self {
+        assert_eq!(pixels.len(), width * height, "pixel count doesn't match {width}x{height}");
+        Framebuffer { width, height, pixels, gamma_correction: false }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixel(&self, i: usize, j: usize) -> Color {
+        self.pixels[j * self.width + i]
+    }
+
+    pub fn set_pixel(&mut self, i: usize, j: usize, color: Color) {
+        self.pixels[j * self.width + i] = color;
+    }
+
+    // Translates the [0, 1] component values to the byte range [0, 255].
+    pub fn to_rgb8(&self) -> Vec<u8> {
+        let intensity = Interval::new(0.000, 0.999);
+        self.pixels.iter()
+            .flat_map(|pixel| [pixel.x, pixel.y, pixel.z])
+            .map(|c| if self.gamma_correction { linear_to_gamma(c) } else { c })
+            .map(|c| (255.999 * intensity.clamp(c)).floor() as u8)
+            .collect()
+    }
+
+    /// Writes the image to `file_path`, creating its directory if needed. The encoder is picked
+    /// from the file extension: `.ppm` for binary PPM (P6) or `.png` for 8-bit images, `.hdr`
+    /// for Radiance RGBE or `.exr` for OpenEXR to keep the linear floating point values.
+    pub fn save(&self, file_path: &str) -> io::Result<()> {
+        let path = Path::new(file_path);
+        let extension = path.extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+        let encode = match extension.as_deref() {
+            Some("ppm") => write_ppm,
+            Some("png") => write_png,
+            Some("hdr") => write_hdr,
+            Some("exr") => write_exr,
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                           format!("unsupported image format: {file_path}"))),
+        };
+
+        let mut out = create(path)?;
+        encode(self, &mut out)?;
+        out.flush()
+    }
+
+    /// Writes the image to the OpenEXR file `file_path`, with `layers` as more channels next to
+    /// R, G and B. Channels are named `layer.channel` by convention, e.g. `normal.X`.
+    pub fn save_with_layers(&self, file_path: &str, layers: &[(&str, Vec<f32>)])
+                            -> io::Result<()> {
+        let mut out = create(Path::new(file_path))?;
+        let mut channels = rgb_channels(self);
+        channels.extend_from_slice(layers);
+        write_exr_channels(&mut out, self.width, self.height, &channels)?;
+        out.flush()
+    }
+}
+
+// Creates the file at `path` to write to, and its directory if needed.
+fn create(path: &Path) -> io::Result<BufWriter<File>> {
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+    Ok(BufWriter::new(File::create(path)?))
+}
+
+impl Framebuffer {
+    /// Reads an image into a framebuffer, picking the decoder from the file extension: `.ppm`
+    /// (P3 or P6) or `.png` for 8 or 16-bit images, or `.hdr` for Radiance RGBE.
+    ///
+    /// 8 and 16-bit images are taken to be gamma encoded, the way `save` writes them and the way
+    /// image editors store pictures, so their values are decoded back to linear. HDR images are
+    /// linear already. Images without any pixels are an error, since there's nothing to look up.
+    pub fn load(file_path: &str) -> io::Result<Framebuffer> {
+        let extension = Path::new(file_path).extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+        let decode = match extension.as_deref() {
+            Some("ppm") => read_ppm,
+            Some("png") => read_png,
+            Some("hdr") => read_hdr,
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                           format!("unsupported image format: {file_path}"))),
+        };
+        let framebuffer = decode(&mut BufReader::new(File::open(file_path)?))?;
+        if framebuffer.width == 0 || framebuffer.height == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                      format!("image has no pixels: {file_path}")));
+        }
+        Ok(framebuffer)
+    }
+}
+
+// Binary PPM: the same header as the plain text P3 format, followed by raw RGB bytes.
+fn write_ppm(framebuffer: &Framebuffer, out: &mut dyn Write) -> io::Result<()> {
+    write!(out, "P6\n{} {}\n255\n", framebuffer.width, framebuffer.height)?;
+    out.write_all(&framebuffer.to_rgb8())
+}
+
+fn write_png(framebuffer: &Framebuffer, out: &mut dyn Write) -> io::Result<()> {
+    let mut encoder = png::Encoder::new(out, framebuffer.width as u32, framebuffer.height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&framebuffer.to_rgb8())?;
+    writer.finish()?;
+    Ok(())
+}
+
+// Reads both the plain text P3 and the binary P6 formats. The header is whitespace separated
+// tokens, with `#` comments: the magic number, width, height and the maximum component value,
+// followed by one more whitespace character in P6. Components above 255 take two bytes in P6.
+fn read_ppm(input: &mut dyn BufRead) -> io::Result<Framebuffer> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData,
+                                                 format!("invalid PPM file: {message}"));
+
+    let mut bytes = Vec::new();
+    input.read_to_end(&mut bytes)?;
+    let mut position = 0;
+    // Returns the next header token, leaving `position` on the whitespace after it.
+    let mut token = || -> Option<&str> {
+        loop {
+            match bytes.get(position)? {
+                b'#' => while bytes.get(position).is_some_and(|&b| b != b'\n') {
+                    position += 1;
+                },
+                b if b.is_ascii_whitespace() => position += 1,
+                _ => break,
+            }
+        }
+        let start = position;
+        while bytes.get(position).is_some_and(|b| !b.is_ascii_whitespace()) {
+            position += 1;
+        }
+        std::str::from_utf8(&bytes[start..position]).ok()
+    };
+
+    let binary = match token() {
+        Some("P3") => false,
+        Some("P6") => true,
+        _ => return Err(invalid("only P3 and P6 are supported")),
+    };
+    let mut number = || token().and_then(|token| token.parse::<usize>().ok());
+    let (Some(width), Some(height), Some(max_value)) = (number(), number(), number()) else {
+        return Err(invalid("bad header"));
+    };
+    if !(1..=65535).contains(&max_value) {
+        return Err(invalid("maximum value must be between 1 and 65535"));
+    }
+
+    let Some(count) = width.checked_mul(height).and_then(|pixels| pixels.checked_mul(3)) else {
+        return Err(invalid("image too large"));
+    };
+    let values: Vec<usize> = if binary {
+        let data = bytes.get(position + 1..).unwrap_or(&[]);
+        let values: Vec<usize> = if max_value < 256 {
+            data.iter().map(|&b| usize::from(b)).collect()
+        } else {
+            data.chunks_exact(2).map(|b| usize::from(b[0]) << 8 | usize::from(b[1])).collect()
+        };
+        values.into_iter().take(count).collect()
+    } else {
+        std::iter::from_fn(&mut number).take(count).collect()
+    };
+    if values.len() < count {
+        return Err(invalid("not enough pixel data"));
+    }
+
+    let component = |value: usize| gamma_to_linear(value as f64 / max_value as f64);
+    let pixels = values.chunks_exact(3)
+        .map(|rgb| Color::new(component(rgb[0]), component(rgb[1]), component(rgb[2])))
+        .collect();
+    Ok(Framebuffer::from_pixels(width, height, pixels))
+}
+
+// Grayscale images are spread out to RGB, palettes are looked up and alpha is dropped.
+fn read_png(input: &mut dyn BufRead) -> io::Result<Framebuffer> {
+    let mut decoder = png::Decoder::new(input);
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf)?;
+
+    let channels = info.color_type.samples();
+    let max_value = match info.bit_depth {
+        png::BitDepth::Sixteen => 65535.0,
+        _ => 255.0,
+    };
+    let values: Vec<f64> = match info.bit_depth {
+        png::BitDepth::Sixteen => buf[..info.buffer_size()].chunks_exact(2)
+            .map(|b| f64::from(u16::from_be_bytes([b[0], b[1]])))
+            .collect(),
+        _ => buf[..info.buffer_size()].iter().map(|&b| f64::from(b)).collect(),
+    };
+
+    let component = |value: f64| gamma_to_linear(value / max_value);
+    let pixels = values.chunks_exact(channels)
+        .map(|sample| match channels {
+            1 | 2 => Color::new(component(sample[0]), component(sample[0]), component(sample[0])),
+            _ => Color::new(component(sample[0]), component(sample[1]), component(sample[2])),
+        })
+        .collect();
+    Ok(Framebuffer::from_pixels(info.width as usize, info.height as usize, pixels))
+}
+
+// Image viewers assume images are "gamma corrected": the stored values have been transformed so
+// that more of the byte range goes to the dark tones our eyes are sensitive to. Our renders are
+// computed in linear space, so we apply the inverse of gamma 2, i.e. the square root.
+fn linear_to_gamma(linear_component: f64) -> f64 {
+    f64::sqrt(linear_component)
+}
+
+fn gamma_to_linear(gamma_component: f64) -> f64 {
+    gamma_component * gamma_component
+}
+
+// Radiance HDR stores each pixel as four bytes (RGBE): an 8-bit mantissa per channel and an
+// exponent shared by all three. We write flat (not run length encoded) scanlines, which every
+// reader accepts.
+fn write_hdr(framebuffer: &Framebuffer, out: &mut dyn Write) -> io::Result<()> {
+    write!(out, "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+           framebuffer.height, framebuffer.width)?;
+    let bytes: Vec<u8> = framebuffer.pixels.iter().flat_map(|&pixel| to_rgbe(pixel)).collect();
+    out.write_all(&bytes)
+}
+
+fn to_rgbe(color: Color) -> [u8; 4] {
+    let (r, g, b) = (f64::max(color.x, 0.0), f64::max(color.y, 0.0), f64::max(color.z, 0.0));
+    let v = f64::max(r, f64::max(g, b));
+    if v < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+
+    // Find e such that v = m * 2^e with m in [0.5, 1), then store each channel as c / 2^e * 256.
+    // The exponent byte only goes up to 2^127, so anything brighter, infinity included, is
+    // written as the brightest value there is.
+    let e = if v.is_finite() { (v.log2().floor() as i32 + 1).clamp(-127, 127) } else { 127 };
+    let scale = 256.0 / 2f64.powi(e);
+    let mantissa = |c: f64| f64::min(c * scale, 255.0) as u8;
+    [mantissa(r), mantissa(g), mantissa(b), (e + 128) as u8]
+}
+
+// Reads what `write_hdr` writes, plus the run length encoded scanlines most other programs write:
+// a scanline starting with the bytes 2, 2 and the width stores the four components one after
+// another, each as runs (a count above 128, then one byte to repeat count - 128 times) and
+// literal spans (a count of at most 128, then that many bytes).
+// Far wider than any environment map, even ones meant for printing.
+const MAX_HDR_WIDTH: usize = 1 << 16;
+
+fn read_hdr(input: &mut dyn BufRead) -> io::Result<Framebuffer> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData,
+                                                 format!("invalid Radiance HDR file: {message}"));
+
+    let mut line = String::new();
+    input.read_line(&mut line)?;
+    if !line.starts_with("#?") {
+        return Err(invalid("missing #? signature"));
+    }
+    loop {
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            return Err(invalid("missing resolution"));
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                return Err(invalid(&format!("unsupported format {format}")));
+            }
+        }
+    }
+
+    line.clear();
+    input.read_line(&mut line)?;
+    let (height, width) = match line.split_whitespace().collect::<Vec<_>>()[..] {
+        ["-Y", height, "+X", width] => (height.parse::<usize>(), width.parse::<usize>()),
+        _ => return Err(invalid("only -Y height +X width orientation is supported")),
+    };
+    let (Ok(height), Ok(width)) = (height, width) else {
+        return Err(invalid("bad resolution"));
+    };
+
+    // A scanline is allocated before reading any of it, so its width has to be checked first.
+    // The height isn't, since the pixels are only stored as they're read.
+    if width == 0 || height == 0 {
+        return Err(invalid("image has no pixels"));
+    }
+    if width > MAX_HDR_WIDTH || width.checked_mul(height).is_none() {
+        return Err(invalid("image too large"));
+    }
+
+    // Not allocated up front, since the resolution could be anything.
+    let mut pixels = Vec::new();
+    let mut scanline = vec![0u8; width * 4];
+    for _ in 0..height {
+        let mut start = [0u8; 4];
+        input.read_exact(&mut start)?;
+        let is_rle = (8..32768).contains(&width) && start[0] == 2 && start[1] == 2
+            && (usize::from(start[2]) << 8 | usize::from(start[3])) == width;
+
+        if is_rle {
+            for component in 0..4 {
+                let mut i = 0;
+                while i < width {
+                    let mut count = [0u8; 1];
+                    input.read_exact(&mut count)?;
+                    let (count, run) = match count[0] {
+                        c if c > 128 => (usize::from(c - 128), true),
+                        c => (usize::from(c), false),
+                    };
+                    if count == 0 || i + count > width {
+                        return Err(invalid("bad scanline run"));
+                    }
+                    let mut value = [0u8; 1];
+                    for k in 0..count {
+                        // A run repeats one byte, a literal span has a byte per pixel.
+                        if k == 0 || !run {
+                            input.read_exact(&mut value)?;
+                        }
+                        scanline[i * 4 + component] = value[0];
+                        i += 1;
+                    }
+                }
+            }
+        } else {
+            scanline[..4].copy_from_slice(&start);
+            input.read_exact(&mut scanline[4..])?;
+        }
+
+        pixels.extend(scanline.chunks_exact(4)
+            .map(|rgbe| from_rgbe([rgbe[0], rgbe[1], rgbe[2], rgbe[3]])));
+    }
+
+    Ok(Framebuffer::from_pixels(width, height, pixels))
+}
+
+// The inverse of `to_rgbe`, taking each mantissa to the middle of the range it stands for.
+fn from_rgbe(rgbe: [u8; 4]) -> Color {
+    if rgbe[3] == 0 {
+        return Color::new(0.0, 0.0, 0.0);
+    }
+    let scale = 2f64.powi(i32::from(rgbe[3]) - 128) / 256.0;
+    let component = |m: u8| (f64::from(m) + 0.5) * scale;
+    Color::new(component(rgbe[0]), component(rgbe[1]), component(rgbe[2]))
+}
+
+fn write_exr(framebuffer: &Framebuffer, out: &mut dyn Write) -> io::Result<()> {
+    write_exr_channels(out, framebuffer.width, framebuffer.height, &rgb_channels(framebuffer))
+}
+
+fn rgb_channels(framebuffer: &Framebuffer) -> Vec<(&'static str, Vec<f32>)> {
+    let channel = |component: fn(&Color) -> f64| -> Vec<f32> {
+        framebuffer.pixels.iter().map(|pixel| component(pixel) as f32).collect()
+    };
+    vec![
+        ("R", channel(|pixel| pixel.x)),
+        ("G", channel(|pixel| pixel.y)),
+        ("B", channel(|pixel| pixel.z)),
+    ]
+}
+
+// A minimal single-part, scanline, uncompressed OpenEXR writer with 32-bit float channels.
+//
+// The file is laid out as:
+//     1. The magic number and version flags.
+//     2. The header: a list of (name, type, size, value) attributes terminated by an empty name.
+//     3. The offset table: the absolute file position of every scanline block.
+//     4. The scanline blocks: the y coordinate, the data size, then each channel's row in turn.
+// Everything is little endian, and channels must be stored in alphabetical order of name.
+fn write_exr_channels(out: &mut dyn Write, width: usize, height: usize,
+                      channels: &[(&str, Vec<f32>)]) -> io::Result<()> {
+    let mut channels: Vec<&(&str, Vec<f32>)> = channels.iter().collect();
+    channels.sort_by_key(|(name, _)| *name);
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&[0x76, 0x2f, 0x31, 0x01]);
+    header.extend_from_slice(&2u32.to_le_bytes());
+
+    let mut chlist = Vec::new();
+    for (name, _) in &channels {
+        chlist.extend_from_slice(name.as_bytes());
+        chlist.push(0);
+        chlist.extend_from_slice(&2i32.to_le_bytes()); // pixel type: FLOAT
+        chlist.extend_from_slice(&[0, 0, 0, 0]); // pLinear and reserved
+        chlist.extend_from_slice(&1i32.to_le_bytes()); // x sampling
+        chlist.extend_from_slice(&1i32.to_le_bytes()); // y sampling
+    }
+    chlist.push(0);
+
+    let mut window = Vec::new();
+    for v in [0, 0, width as i32 - 1, height as i32 - 1] {
+        window.extend_from_slice(&v.to_le_bytes());
+    }
+
+    let mut attribute = |name: &str, kind: &str, value: &[u8]| {
+        header.extend_from_slice(name.as_bytes());
+        header.push(0);
+        header.extend_from_slice(kind.as_bytes());
+        header.push(0);
+        header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+        header.extend_from_slice(value);
+    };
+    attribute("channels", "chlist", &chlist);
+    attribute("compression", "compression", &[0]);
+    attribute("dataWindow", "box2i", &window);
+    attribute("displayWindow", "box2i", &window);
+    attribute("lineOrder", "lineOrder", &[0]);
+    attribute("pixelAspectRatio", "float", &1f32.to_le_bytes());
+    attribute("screenWindowCenter", "v2f", &[0; 8]);
+    attribute("screenWindowWidth", "float", &1f32.to_le_bytes());
+    header.push(0);
+
+    let block_size = 8 + channels.len() * width * 4;
+    let first_block = header.len() + height * 8;
+    for y in 0..height {
+        header.extend_from_slice(&((first_block + y * block_size) as u64).to_le_bytes());
+    }
+    out.write_all(&header)?;
+
+    let mut block = Vec::with_capacity(block_size);
+    for y in 0..height {
+        block.clear();
+        block.extend_from_slice(&(y as i32).to_le_bytes());
+        block.extend_from_slice(&((block_size - 8) as i32).to_le_bytes());
+        for (_, values) in &channels {
+            for v in &values[y * width..(y + 1) * width] {
+                block.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+        out.write_all(&block)?;
+    }
+    Ok(())
+}
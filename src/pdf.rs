@@ -0,0 +1,103 @@
+use std::f64::consts::PI;
+use crate::hittable::Hittable;
+use crate::onb::Onb;
+use crate::utils::{random_double, SmallRng};
+use crate::vec3::{dot, Point3, random_cosine_direction, random_unit_vector, unit_vector, Vec3};
+
+// https://raytracing.github.io/books/RayTracingTheRestOfYourLife.html#samplinglightsdirectly
+// A probability density function over directions. Monte Carlo integration converges fastest when
+// samples are drawn in proportion to what's being integrated, so rather than always scattering
+// the way the material does, we can also aim rays at the lights, or mix the two, as long as each
+// sample is divided by the density it was drawn with.
+pub trait Pdf {
+    // The density of `generate` picking `direction`, per unit of solid angle.
+    fn value(&self, direction: Vec3) -> f64;
+
+    // A random direction drawn from this distribution.
+    fn generate(&self, rng: &mut SmallRng) -> Vec3;
+}
+
+// Every direction equally likely: the whole sphere is 4pi steradians.
+pub struct SpherePdf;
+
+impl Pdf for SpherePdf {
+    fn value(&self, _direction: Vec3) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+
+    fn generate(&self, rng: &mut SmallRng) -> Vec3 {
+        random_unit_vector(rng)
+    }
+}
+
+// Directions on the hemisphere around a surface normal, weighted by cos(theta).
+pub struct CosinePdf {
+    uvw: Onb,
+}
+
+impl CosinePdf {
+    pub fn new(w: Vec3) -> Self {
+        Self { uvw: Onb::new(w) }
+    }
+}
+
+impl Pdf for CosinePdf {
+    fn value(&self, direction: Vec3) -> f64 {
+        let cosine_theta = dot(unit_vector(direction), self.uvw.w);
+        f64::max(0.0, cosine_theta / PI)
+    }
+
+    fn generate(&self, rng: &mut SmallRng) -> Vec3 {
+        self.uvw.transform(random_cosine_direction(rng))
+    }
+}
+
+// Directions from `origin` towards random points on `objects`, using their `random` and
+// `pdf_value`.
+pub struct HittablePdf<'a> {
+    objects: &'a dyn Hittable,
+    origin: Point3,
+}
+
+impl<'a> HittablePdf<'a> {
+    pub fn new(objects: &'a dyn Hittable, origin: Point3) -> Self {
+        Self { objects, origin }
+    }
+}
+
+impl Pdf for HittablePdf<'_> {
+    fn value(&self, direction: Vec3) -> f64 {
+        self.objects.pdf_value(self.origin, direction)
+    }
+
+    fn generate(&self, rng: &mut SmallRng) -> Vec3 {
+        self.objects.random(self.origin, rng)
+    }
+}
+
+// Half the time draws from one distribution and half the time from the other, so its density is
+// the average of theirs. Mixing light sampling with the material's own distribution keeps the
+// low noise of aiming at lights without losing the directions the lights don't cover.
+pub struct MixturePdf<'a> {
+    p: [&'a dyn Pdf; 2],
+}
+
+impl<'a> MixturePdf<'a> {
+    pub fn new(p0: &'a dyn Pdf, p1: &'a dyn Pdf) -> Self {
+        Self { p: [p0, p1] }
+    }
+}
+
+impl Pdf for MixturePdf<'_> {
+    fn value(&self, direction: Vec3) -> f64 {
+        0.5 * self.p[0].value(direction) + 0.5 * self.p[1].value(direction)
+    }
+
+    fn generate(&self, rng: &mut SmallRng) -> Vec3 {
+        if random_double!(rng) < 0.5 {
+            self.p[0].generate(rng)
+        } else {
+            self.p[1].generate(rng)
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use crate::utils::{random_double, SmallRng};
+use crate::vec3::{dot, Point3, unit_vector, Vec3};
+
+const POINT_COUNT: usize = 256;
+
+// https://raytracing.github.io/books/RayTracingTheNextWeek.html#perlinnoise
+// Perlin noise is repeatable random-looking noise: the same point always gives the same value,
+// and nearby points give similar values.
+//
+// Every point on the integer lattice gets a random unit vector, picked by hashing its
+// coordinates through three random permutations. The noise at a point is the dot products of
+// the eight surrounding lattice vectors with the offsets to the point, smoothly interpolated.
+// Using vectors rather than plain random values moves the minima and maxima off the lattice, so
+// the result doesn't look blocky.
+pub struct Perlin {
+    randvec: [Vec3; POINT_COUNT],
+    perm_x: [usize; POINT_COUNT],
+    perm_y: [usize; POINT_COUNT],
+    perm_z: [usize; POINT_COUNT],
+}
+
+impl Perlin {
+    pub fn new(rng: &mut SmallRng) -> Perlin {
+        let mut randvec = [Vec3::default(); POINT_COUNT];
+        for v in &mut randvec {
+            *v = unit_vector(Vec3::random(rng, -1.0, 1.0));
+        }
+        Perlin {
+            randvec,
+            perm_x: generate_perm(rng),
+            perm_y: generate_perm(rng),
+            perm_z: generate_perm(rng),
+        }
+    }
+
+    // Noise in [-1, 1].
+    pub fn noise(&self, p: Point3) -> f64 {
+        let u = p.x - p.x.floor();
+        let v = p.y - p.y.floor();
+        let w = p.z - p.z.floor();
+        let i = p.x.floor() as i64;
+        let j = p.y.floor() as i64;
+        let k = p.z.floor() as i64;
+
+        let mut c = [[[Vec3::default(); 2]; 2]; 2];
+        for (di, plane) in c.iter_mut().enumerate() {
+            for (dj, row) in plane.iter_mut().enumerate() {
+                for (dk, corner) in row.iter_mut().enumerate() {
+                    *corner = self.randvec[self.perm_x[((i + di as i64) & 255) as usize]
+                        ^ self.perm_y[((j + dj as i64) & 255) as usize]
+                        ^ self.perm_z[((k + dk as i64) & 255) as usize]];
+                }
+            }
+        }
+
+        perlin_interp(&c, u, v, w)
+    }
+
+    // Turbulence: a sum of `depth` octaves of noise, each at twice the frequency and half the
+    // amplitude of the one before.
+    pub fn turb(&self, p: Point3, depth: i32) -> f64 {
+        let mut accum = 0.0;
+        let mut temp_p = p;
+        let mut weight = 1.0;
+
+        for _ in 0..depth {
+            accum += weight * self.noise(temp_p);
+            weight *= 0.5;
+            temp_p *= 2.0;
+        }
+
+        accum.abs()
+    }
+}
+
+// A random permutation of 0..POINT_COUNT (Fisher–Yates).
+fn generate_perm(rng: &mut SmallRng) -> [usize; POINT_COUNT] {
+    let mut p = [0; POINT_COUNT];
+    for (i, v) in p.iter_mut().enumerate() {
+        *v = i;
+    }
+    for i in (1..POINT_COUNT).rev() {
+        let target = (random_double!(rng) * (i + 1) as f64) as usize;
+        p.swap(i, target);
+    }
+    p
+}
+
+// Trilinear interpolation, with the weights smoothed by a Hermite cubic so the lattice doesn't
+// show through as Mach bands.
+fn perlin_interp(c: &[[[Vec3; 2]; 2]; 2], u: f64, v: f64, w: f64) -> f64 {
+    let uu = u * u * (3.0 - 2.0 * u);
+    let vv = v * v * (3.0 - 2.0 * v);
+    let ww = w * w * (3.0 - 2.0 * w);
+    let mut accum = 0.0;
+
+    for (i, plane) in c.iter().enumerate() {
+        for (j, row) in plane.iter().enumerate() {
+            for (k, corner) in row.iter().enumerate() {
+                let (fi, fj, fk) = (i as f64, j as f64, k as f64);
+                let weight_v = Vec3::new(u - fi, v - fj, w - fk);
+                accum += (fi * uu + (1.0 - fi) * (1.0 - uu))
+                    * (fj * vv + (1.0 - fj) * (1.0 - vv))
+                    * (fk * ww + (1.0 - fk) * (1.0 - ww))
+                    * dot(*corner, weight_v);
+            }
+        }
+    }
+
+    accum
+}
@@ -0,0 +1,69 @@
+use std::sync::Arc;
+use crate::aabb::Aabb;
+use crate::hittable::{object_id, HitRecord, Hittable};
+use crate::interval::{self, Interval};
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::vec3::{dot, Point3, Vec3};
+
+/// An infinite plane through `point`, facing `normal`.
+///
+/// Unlike the huge sphere the book uses for the ground, a plane is actually flat all the way to
+/// the horizon. Its texture coordinates are distances along the plane, so they keep growing
+/// rather than wrapping around.
+pub struct Plane {
+    point: Point3,
+    basis: Onb,
+    mat: Option<Arc<dyn Material>>,
+    bbox: Aabb,
+}
+
+impl Plane {
+    pub fn new(point: Point3, normal: Vec3, mat: Option<Arc<dyn Material>>) -> Plane {
+        let basis = Onb::new(normal);
+        // An infinite plane has an infinite bounding box, except along its normal if that's one
+        // of the axes, where it's as thin as the plane itself.
+        let bound = |axis: usize| if basis.w[axis].abs() == 1.0 {
+            Interval::new(point[axis], point[axis])
+        } else {
+            interval::UNIVERSE
+        };
+        let bbox = Aabb::from_intervals(bound(0), bound(1), bound(2));
+        Plane { point, basis, mat, bbox }
+    }
+}
+
+impl Hittable for Plane {
+    // Every point P on the plane satisfies n · (P - point) = 0. Substituting the ray P(t) = Q + td
+    // and solving for t gives
+    //     t = n · (point - Q) / (n · d)
+    // unless n · d is zero, in which case the ray runs parallel to the plane.
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let normal = self.basis.w;
+        let denom = dot(normal, r.direction);
+        if denom.abs() < 1e-8 {
+            return false;
+        }
+
+        let t = dot(normal, self.point - r.origin) / denom;
+        if !ray_t.surrounds(t) {
+            return false;
+        }
+
+        rec.t = t;
+        rec.p = Some(r.at(t));
+        let offset = rec.p.unwrap() - self.point;
+        rec.u = dot(offset, self.basis.u);
+        rec.v = dot(offset, self.basis.v);
+        rec.set_face_normal(r, normal);
+        rec.mat = self.mat.as_ref().map(Arc::clone);
+        rec.object_id = object_id(self);
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
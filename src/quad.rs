@@ -0,0 +1,146 @@
+use std::sync::Arc;
+use crate::aabb::Aabb;
+use crate::hittable::{self, object_id, HitRecord, Hittable, HittableList};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::utils::{random_double, SmallRng};
+use crate::vec3::{cross, dot, Point3, unit_vector, Vec3};
+
+/// A parallelogram with one corner at `q` and sides `u` and `v`, so the other corners are at
+/// q + u, q + v and q + u + v. Its front face is the side `u` × `v` points to.
+// https://raytracing.github.io/books/RayTracingTheNextWeek.html#quadrilaterals
+pub struct Quad {
+    q: Point3,
+    u: Vec3,
+    v: Vec3,
+    // n / (n · n), which turns a point on the plane into its (alpha, beta) coordinates.
+    w: Vec3,
+    normal: Vec3,
+    // The plane containing the quad is n · P = d.
+    d: f64,
+    area: f64,
+    mat: Option<Arc<dyn Material>>,
+    bbox: Aabb,
+}
+
+impl Quad {
+    pub fn new(q: Point3, u: Vec3, v: Vec3, mat: Option<Arc<dyn Material>>) -> Quad {
+        let n = cross(u, v);
+        let normal = unit_vector(n);
+        let d = dot(normal, q);
+        let w = n / dot(n, n);
+
+        // Bound both diagonals, since the four corners may come in any order along each axis.
+        let bbox = Aabb::enclosing(&Aabb::from_points(q, q + u + v),
+                                   &Aabb::from_points(q + u, q + v));
+        Quad { q, u, v, w, normal, d, area: n.length(), mat, bbox }
+    }
+}
+
+impl Hittable for Quad {
+    // First intersect the ray with the quad's plane, then check whether the hit point lies inside
+    // the quad. Writing the point as P = Q + αu + βv, the plane coordinates are
+    //     α = w · (p × v)
+    //     β = w · (u × p)
+    // where p = P - Q, and the point is inside when both are in [0, 1].
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let denom = dot(self.normal, r.direction);
+
+        // No hit if the ray is parallel to the plane.
+        if denom.abs() < 1e-8 {
+            return false;
+        }
+
+        // Return false if the hit point parameter t is outside the ray interval.
+        let t = (self.d - dot(self.normal, r.origin)) / denom;
+        if !ray_t.surrounds(t) {
+            return false;
+        }
+
+        let intersection = r.at(t);
+        let planar_hitpt_vector = intersection - self.q;
+        let alpha = dot(self.w, cross(planar_hitpt_vector, self.v));
+        let beta = dot(self.w, cross(self.u, planar_hitpt_vector));
+
+        let unit_interval = Interval::new(0.0, 1.0);
+        if !unit_interval.contains(alpha) || !unit_interval.contains(beta) {
+            return false;
+        }
+
+        rec.t = t;
+        rec.p = Some(intersection);
+        rec.u = alpha;
+        rec.v = beta;
+        rec.set_face_normal(r, self.normal);
+        rec.mat = self.mat.as_ref().map(Arc::clone);
+        rec.object_id = object_id(self);
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        hittable::planar_pdf_value(self, self.area, origin, direction)
+    }
+
+    fn random(&self, origin: Point3, rng: &mut SmallRng) -> Vec3 {
+        let p = self.q + (random_double!(rng) * self.u) + (random_double!(rng) * self.v);
+        p - origin
+    }
+}
+
+/// An axis-aligned box with opposite corners `a` and `b`, made of six quads facing outward.
+pub struct Box {
+    sides: HittableList,
+}
+
+impl Box {
+    pub fn new(a: Point3, b: Point3, mat: Option<Arc<dyn Material>>) -> Box {
+        let min = Point3::new(f64::min(a.x, b.x), f64::min(a.y, b.y), f64::min(a.z, b.z));
+        let max = Point3::new(f64::max(a.x, b.x), f64::max(a.y, b.y), f64::max(a.z, b.z));
+
+        let dx = Vec3::new(max.x - min.x, 0.0, 0.0);
+        let dy = Vec3::new(0.0, max.y - min.y, 0.0);
+        let dz = Vec3::new(0.0, 0.0, max.z - min.z);
+
+        let mut sides = HittableList::new();
+        let mut side = |q: Point3, u: Vec3, v: Vec3| {
+            sides.add(Arc::new(Quad::new(q, u, v, mat.clone())));
+        };
+        side(Point3::new(min.x, min.y, max.z), dx, dy);  // front
+        side(Point3::new(max.x, min.y, max.z), -dz, dy); // right
+        side(Point3::new(max.x, min.y, min.z), -dx, dy); // back
+        side(Point3::new(min.x, min.y, min.z), dz, dy);  // left
+        side(Point3::new(min.x, max.y, max.z), dx, -dz); // top
+        side(Point3::new(min.x, min.y, min.z), dx, dz);  // bottom
+
+        Box { sides }
+    }
+}
+
+impl Hittable for Box {
+    // The box is one object, not six.
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        if !self.sides.hit(r, ray_t, rec) {
+            return false;
+        }
+        rec.object_id = object_id(self);
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.sides.bounding_box()
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        self.sides.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: Point3, rng: &mut SmallRng) -> Vec3 {
+        self.sides.random(origin, rng)
+    }
+}
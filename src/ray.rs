@@ -1,17 +1,21 @@
 use crate::vec3::{Point3, Vec3};
 
-pub(crate) struct Ray {
-    pub(crate) origin: Point3,
-    pub(crate) direction: Vec3,
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Ray {
+    pub origin: Point3,
+    pub direction: Vec3,
+    // The moment the ray was sent, for scenes where things move while the shutter is open.
+    pub time: f64,
 }
 
 /// https://raytracing.github.io/books/RayTracingInOneWeekend.html#rays,asimplecamera,andbackground
 impl Ray {
-    pub(crate) fn new(origin: Point3, direction: Vec3) -> Self {
-        Self { origin, direction }
+    pub fn new(origin: Point3, direction: Vec3) -> Self {
+        Self::with_time(origin, direction, 0.0)
     }
-    pub(crate) fn default() -> Self {
-        Self { origin: Point3::default(), direction : Vec3::default() }
+
+    pub fn with_time(origin: Point3, direction: Vec3, time: f64) -> Self {
+        Self { origin, direction, time }
     }
 
     /// P(t) = A + tb.
@@ -19,8 +23,9 @@ impl Ray {
     /// - A is the ray's point of `origin`.
     /// - b is the ray's `direction`.
     /// - `t` is a scalar that when plugged into P, moves the point along the ray.
+    ///
     /// Positive `t` gets parts in front of A and negative `t`, parts behind A.
-    pub(crate) fn at(&self, t: f64) -> Vec3 {
+    pub fn at(&self, t: f64) -> Vec3 {
         self.origin + (self.direction * t)
     }
 }
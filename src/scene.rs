@@ -0,0 +1,899 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::{fmt, fs, io};
+use serde::Deserialize;
+use crate::background::Background;
+use crate::bvh::BvhNode;
+use crate::camera::Camera;
+use crate::constant_medium::ConstantMedium;
+use crate::disk::Disk;
+use crate::hittable::{Hittable, HittableList};
+use crate::interval::Interval;
+use crate::material::{Dielectric, DiffuseLight, Isotropic, Lambertian, Material, Metal};
+use crate::mesh::{Mesh, MeshError};
+use crate::output::Framebuffer;
+use crate::plane::Plane;
+use crate::quad::{self, Quad};
+use crate::sphere::Sphere;
+use crate::texture::{CheckerTexture, ImageTexture, NoiseStyle, NoiseTexture, SolidColor, Texture};
+use crate::transform::{Matrix4, Transform};
+use crate::utils::{self, random_double, SmallRng};
+use crate::vec3::{Color, cross, Point3, unit_vector, Vec3};
+
+// A scene file is TOML describing the camera, a table of named materials, and a list of objects
+// that refer to those materials by name:
+//
+//     background = [0, 0, 0]   # optional, the sky gradient from the book if left out. Also
+//                              # { type = "gradient", bottom = [..], top = [..] } or
+//                              # { type = "environment", path = "sky.hdr" }, relative to the
+//                              # scene file
+//
+//     [camera]
+//     image_width = 400
+//     aspect_ratio = 1.7778
+//     samples_per_pixel = 100
+//     max_depth = 50
+//     lookfrom = [-2, 2, 1]    # optional, as are vfov, lookat, vup, defocus_angle, focus_dist
+//     shutter = [0, 1]         # and the times the shutter is open between, for motion blur
+//
+//     [materials.ground]
+//     type = "lambertian"
+//     albedo = [0.8, 0.8, 0.0]
+//
+//     [materials.lamp]
+//     type = "diffuse_light"
+//     emit = [4, 4, 4]
+//
+// Albedos and emitted colors can also be textures:
+//
+//     albedo = { type = "checker", scale = 0.5, even = [0, 0, 0], odd = [1, 1, 1] }
+//     albedo = { type = "image", path = "earth.png" }      # relative to the scene file
+//     albedo = { type = "noise", scale = 4, style = "marble" }  # or "smooth", "turbulence"
+//
+// The two sides of a checker are textures themselves, so they can be nested.
+//
+//     [[objects]]
+//     type = "sphere"
+//     center = [0, -100.5, -1]
+//     radius = 100
+//     material = "ground"
+//     center2 = [0, -100, -1]  # optional, makes a sphere that moves from `center` at time 0 to
+//                              # `center2` at time 1
+//
+//     [[objects]]
+//     type = "mesh"
+//     path = "teapot.obj"      # relative to the scene file; materials come from its .mtl files
+//     material = "ground"      # optional, for faces the OBJ file doesn't give a material
+//
+// Objects made of a `diffuse_light` material are also sampled directly as lights, which cuts
+// down on noise a lot when they're small. Planes and volumes are left out, since there's no
+// sampling points on them evenly.
+//
+// Giving an object a `density` fills its shape with fog or smoke of that density instead, which
+// scatters rays with its material, usually `type = "isotropic"` with an `albedo`. The density has
+// to be positive, and the shape closed and convex, like a sphere or a box: a plane, quad or disk
+// has no inside to fill.
+//
+// Any object can be placed with a `transform`, applied as scale, then rotation, then translation.
+// Rotations are in degrees around the x, y and z axes, in that order. A mesh file used several
+// times is only loaded once, and each transformed copy shares it:
+//
+//     [[objects]]
+//     type = "mesh"
+//     path = "teapot.obj"
+//     transform = { scale = 0.5, rotate = [0, 45, 0], translate = [1, 0, 0] }
+//
+// See `scenes/` for complete examples.
+
+/// The world, camera and background described by a scene file, ready to render.
+pub struct Scene {
+    pub world: HittableList,
+    // The emitters in `world` that bounces aim rays at, which may be empty.
+    pub lights: HittableList,
+    pub camera: Camera,
+    pub background: Background,
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    UnknownMaterial { object: usize, name: String },
+    InvalidTransform { object: usize },
+    InvalidDensity { object: usize },
+    OpenVolume { object: usize },
+    InvalidCamera { setting: &'static str },
+    Environment { path: PathBuf, err: io::Error },
+    Texture { path: PathBuf, err: io::Error },
+    Mesh(MeshError),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io(err) => write!(f, "unable to read scene file: {err}"),
+            SceneError::Parse(err) => write!(f, "invalid scene file: {err}"),
+            SceneError::UnknownMaterial { object, name } =>
+                write!(f, "objects[{object}] refers to unknown material `{name}`"),
+            SceneError::InvalidTransform { object } =>
+                write!(f, "objects[{object}] has a transform that can't be inverted"),
+            SceneError::InvalidDensity { object } =>
+                write!(f, "objects[{object}] has a density that isn't a positive number"),
+            SceneError::OpenVolume { object } =>
+                write!(f, "objects[{object}] can't have a density, its shape isn't closed"),
+            SceneError::InvalidCamera { setting: "vfov" } =>
+                write!(f, "camera.vfov must be between 0 and 180 degrees"),
+            SceneError::InvalidCamera { setting: "lookat" } =>
+                write!(f, "camera.lookat must be somewhere other than camera.lookfrom"),
+            SceneError::InvalidCamera { setting: "vup" } =>
+                write!(f, "camera.vup can't point along the view direction"),
+            SceneError::InvalidCamera { setting } =>
+                write!(f, "camera.{setting} must be a positive number"),
+            SceneError::Environment { path, err } =>
+                write!(f, "unable to read environment map {}: {err}", path.display()),
+            SceneError::Texture { path, err } =>
+                write!(f, "unable to read texture {}: {err}", path.display()),
+            SceneError::Mesh(err) => write!(f, "invalid mesh: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl From<io::Error> for SceneError {
+    fn from(err: io::Error) -> Self {
+        SceneError::Io(err)
+    }
+}
+
+impl From<toml::de::Error> for SceneError {
+    fn from(err: toml::de::Error) -> Self {
+        SceneError::Parse(err)
+    }
+}
+
+impl From<MeshError> for SceneError {
+    fn from(err: MeshError) -> Self {
+        SceneError::Mesh(err)
+    }
+}
+
+pub fn load(file_path: &str) -> Result<Scene, SceneError> {
+    let directory = Path::new(file_path).parent().unwrap_or(Path::new(""));
+    parse_in(&fs::read_to_string(file_path)?, directory)
+}
+
+/// Parses a scene file's contents. Mesh, image and environment map paths are taken relative to
+/// the working directory.
+pub fn parse(contents: &str) -> Result<Scene, SceneError> {
+    parse_in(contents, Path::new(""))
+}
+
+fn parse_in(contents: &str, directory: &Path) -> Result<Scene, SceneError> {
+    let description: SceneDescription = toml::from_str(contents)?;
+
+    // Noise textures are random, but a scene file should always describe the same scene, so
+    // they're drawn from a fixed seed rather than the render's.
+    let mut rng = utils::new_rng(Some(0));
+    // Sorted by name so each noise texture gets the same random numbers every time.
+    let mut names: Vec<&String> = description.materials.keys().collect();
+    names.sort();
+    let materials: HashMap<&str, Arc<dyn Material>> = names.into_iter()
+        .map(|name| Ok((name.as_str(), description.materials[name].build(directory, &mut rng)?)))
+        .collect::<Result<_, SceneError>>()?;
+
+    // Meshes already loaded, by path and default material.
+    let mut meshes: HashMap<(&str, Option<&str>), Arc<Mesh>> = HashMap::new();
+
+    let mut world = HittableList::new();
+    let mut lights = HittableList::new();
+    for (index, object) in description.objects.iter().enumerate() {
+        if let Some(density) = object.density() {
+            if matches!(object, ObjectDescription::Plane { .. } | ObjectDescription::Quad { .. }
+                                | ObjectDescription::Disk { .. }) {
+                return Err(SceneError::OpenVolume { object: index });
+            }
+            if density.is_nan() || density <= 0.0 {
+                return Err(SceneError::InvalidDensity { object: index });
+            }
+        }
+
+        let is_light = matches!(object.material().and_then(|name| description.materials.get(name)),
+                                Some(MaterialDescription::DiffuseLight { .. }))
+            && !matches!(object, ObjectDescription::Plane { .. })
+            && object.density().is_none();
+        let mat = match object.material() {
+            Some(name) => Some(materials.get(name).cloned().ok_or_else(|| {
+                SceneError::UnknownMaterial { object: index, name: name.to_string() }
+            })?),
+            None => None,
+        };
+        // A volume's material belongs to the medium, and its boundary shape doesn't need one.
+        let (mat, phase_function) = match object.density() {
+            Some(_) => (None, mat),
+            None => (mat, None),
+        };
+        let hittable: Arc<dyn Hittable> = match object {
+            ObjectDescription::Sphere { center, center2: None, radius, .. } => {
+                Arc::new(Sphere::new(to_vec3(*center), *radius, mat))
+            }
+            ObjectDescription::Sphere { center, center2: Some(center2), radius, .. } => {
+                Arc::new(Sphere::moving(to_vec3(*center), to_vec3(*center2), *radius, mat))
+            }
+            ObjectDescription::Plane { point, normal, .. } => {
+                Arc::new(Plane::new(to_vec3(*point), to_vec3(*normal), mat))
+            }
+            ObjectDescription::Quad { q, u, v, .. } => {
+                Arc::new(Quad::new(to_vec3(*q), to_vec3(*u), to_vec3(*v), mat))
+            }
+            ObjectDescription::Disk { center, normal, radius, .. } => {
+                Arc::new(Disk::new(to_vec3(*center), to_vec3(*normal), *radius, mat))
+            }
+            ObjectDescription::Box { a, b, .. } => {
+                Arc::new(quad::Box::new(to_vec3(*a), to_vec3(*b), mat))
+            }
+            ObjectDescription::Mesh { path, .. } => {
+                let key = (path.as_str(), mat.as_ref().and(object.material()));
+                match meshes.get(&key) {
+                    Some(mesh) => Arc::clone(mesh) as Arc<dyn Hittable>,
+                    None => {
+                        let path = directory.join(path);
+                        let mesh = Arc::new(Mesh::load(&path.to_string_lossy(), mat)?);
+                        meshes.insert(key, Arc::clone(&mesh));
+                        mesh
+                    }
+                }
+            }
+        };
+
+        let hittable: Arc<dyn Hittable> = match object.transform() {
+            Some(transform) => {
+                let matrix = transform.matrix();
+                if matrix.inverse().is_none() {
+                    return Err(SceneError::InvalidTransform { object: index });
+                }
+                Arc::new(Transform::new(hittable, matrix))
+            }
+            None => hittable,
+        };
+
+        // The volume goes around the transformed shape, so its density is per world unit.
+        match object.density() {
+            Some(density) => {
+                world.add(Arc::new(ConstantMedium::new(hittable, density, phase_function)));
+            }
+            None => {
+                if is_light {
+                    lights.add(Arc::clone(&hittable));
+                }
+                world.add(hittable);
+            }
+        }
+    }
+
+    Ok(Scene {
+        world,
+        lights,
+        camera: description.camera.build()?,
+        background: match description.background {
+            Some(background) => background.build(directory)?,
+            None => Background::sky(),
+        },
+    })
+}
+
+/// Names of the scenes built into the renderer, for `builtin`.
+pub const BUILTIN_SCENES: [&str; 11] = ["metal", "dielectric", "defocus_blur", "final",
+                                       "bouncing_spheres", "checkered_spheres", "perlin_spheres",
+                                       "quads", "simple_light", "cornell_box", "cornell_smoke"];
+
+/// Builds one of the scenes from the book by name, or `None` if there's no such scene. Randomly
+/// generated scenes draw from `rng`.
+pub fn builtin(name: &str, rng: &mut SmallRng) -> Option<Scene> {
+    let mut camera = Camera::new(400, 16.0 / 9.0, 100, 50);
+    let mut background = Background::sky();
+    let mut lights = HittableList::new();
+    let world = match name {
+        // https://raytracing.github.io/books/RayTracingInOneWeekend.html#metal/fuzzyreflection
+        "metal" => three_spheres(Arc::new(Lambertian::new(Color::new(0.7, 0.3, 0.3))),
+                                 Arc::new(Metal::new(Color::new(0.8, 0.8, 0.8), 0.3)),
+                                 Arc::new(Metal::new(Color::new(0.8, 0.6, 0.2), 1.0))),
+        // https://raytracing.github.io/books/RayTracingInOneWeekend.html#dielectrics
+        "dielectric" => glass_spheres(),
+        // https://raytracing.github.io/books/RayTracingInOneWeekend.html#defocusblur
+        "defocus_blur" => {
+            camera.vfov = 20.0;
+            camera.lookfrom = Point3::new(-2.0, 2.0, 1.0);
+            camera.lookat = Point3::new(0.0, 0.0, -1.0);
+            camera.defocus_angle = 10.0;
+            camera.focus_dist = 3.4;
+            glass_spheres()
+        }
+        // https://raytracing.github.io/books/RayTracingInOneWeekend.html#wherenext?/afinalrender
+        "final" => {
+            camera.vfov = 20.0;
+            camera.lookfrom = Point3::new(13.0, 2.0, 3.0);
+            camera.lookat = Point3::new(0.0, 0.0, 0.0);
+            camera.defocus_angle = 0.6;
+            camera.focus_dist = 10.0;
+            random_spheres(rng, false)
+        }
+        // https://raytracing.github.io/books/RayTracingTheNextWeek.html#motionblur/puttingeverythingtogether
+        "bouncing_spheres" => {
+            camera.vfov = 20.0;
+            camera.lookfrom = Point3::new(13.0, 2.0, 3.0);
+            camera.lookat = Point3::new(0.0, 0.0, 0.0);
+            camera.defocus_angle = 0.6;
+            camera.focus_dist = 10.0;
+            camera.shutter = Interval::new(0.0, 1.0);
+            random_spheres(rng, true)
+        }
+        // https://raytracing.github.io/books/RayTracingTheNextWeek.html#texturemapping/rendering3dcheckeredspheres
+        "checkered_spheres" => {
+            camera.vfov = 20.0;
+            camera.lookfrom = Point3::new(13.0, 2.0, 3.0);
+            camera.lookat = Point3::new(0.0, 0.0, 0.0);
+            checkered_spheres()
+        }
+        // https://raytracing.github.io/books/RayTracingTheNextWeek.html#perlinnoise
+        "perlin_spheres" => {
+            camera.vfov = 20.0;
+            camera.lookfrom = Point3::new(13.0, 2.0, 3.0);
+            camera.lookat = Point3::new(0.0, 0.0, 0.0);
+            perlin_spheres(rng)
+        }
+        // https://raytracing.github.io/books/RayTracingTheNextWeek.html#quadrilaterals
+        "quads" => {
+            camera.aspect_ratio = 1.0;
+            camera.vfov = 80.0;
+            camera.lookfrom = Point3::new(0.0, 0.0, 9.0);
+            camera.lookat = Point3::new(0.0, 0.0, 0.0);
+            quads()
+        }
+        // https://raytracing.github.io/books/RayTracingTheNextWeek.html#lights/turningobjectsintolights
+        "simple_light" => {
+            camera.vfov = 20.0;
+            camera.lookfrom = Point3::new(26.0, 3.0, 6.0);
+            camera.lookat = Point3::new(0.0, 2.0, 0.0);
+            background = Background::Solid(Color::new(0.0, 0.0, 0.0));
+            simple_light(&mut lights)
+        }
+        // https://raytracing.github.io/books/RayTracingTheNextWeek.html#instances
+        "cornell_box" => {
+            camera.aspect_ratio = 1.0;
+            camera.vfov = 40.0;
+            camera.lookfrom = Point3::new(278.0, 278.0, -800.0);
+            camera.lookat = Point3::new(278.0, 278.0, 0.0);
+            background = Background::Solid(Color::new(0.0, 0.0, 0.0));
+            cornell_box(false, &mut lights)
+        }
+        // https://raytracing.github.io/books/RayTracingTheNextWeek.html#volumes/renderingacornellboxwithsmokeandfogboxes
+        "cornell_smoke" => {
+            camera.aspect_ratio = 1.0;
+            camera.vfov = 40.0;
+            camera.lookfrom = Point3::new(278.0, 278.0, -800.0);
+            camera.lookat = Point3::new(278.0, 278.0, 0.0);
+            background = Background::Solid(Color::new(0.0, 0.0, 0.0));
+            cornell_box(true, &mut lights)
+        }
+        _ => return None,
+    };
+    Some(Scene { world, lights, camera, background })
+}
+
+// A big sphere for the ground with three spheres sitting side by side on it.
+fn three_spheres(center: Arc<dyn Material>, left: Arc<dyn Material>, right: Arc<dyn Material>)
+                 -> HittableList {
+    let ground: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.0)));
+    let mut world = HittableList::new();
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, -100.5, -1.0), 100.0, Some(ground))));
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, 0.0, -1.0), 0.5, Some(center))));
+    world.add(Arc::new(Sphere::new(Point3::new(-1.0, 0.0, -1.0), 0.5, Some(left))));
+    world.add(Arc::new(Sphere::new(Point3::new(1.0, 0.0, -1.0), 0.5, Some(right))));
+    world
+}
+
+fn glass_spheres() -> HittableList {
+    let glass: Arc<dyn Material> = Arc::new(Dielectric::new(1.5));
+    let mut world = three_spheres(Arc::new(Lambertian::new(Color::new(0.1, 0.2, 0.5))),
+                                  Arc::clone(&glass),
+                                  Arc::new(Metal::new(Color::new(0.8, 0.6, 0.2), 0.0)));
+    // A sphere with a negative radius has its normals pointing inward, so nesting it inside the
+    // glass sphere makes a hollow glass bubble.
+    world.add(Arc::new(Sphere::new(Point3::new(-1.0, 0.0, -1.0), -0.4, Some(glass))));
+    world
+}
+
+// The cover of the first book. With `bouncing`, the small diffuse spheres move upwards while the
+// shutter is open.
+fn random_spheres(rng: &mut SmallRng, bouncing: bool) -> HittableList {
+    let mut world = HittableList::new();
+    let ground_material: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0,
+                                   Some(ground_material))));
+
+    for a in -11..11 {
+        for b in -11..11 {
+            let choose_mat = random_double!(rng);
+            let center = Point3::new(a as f64 + 0.9 * random_double!(rng), 0.2,
+                                     b as f64 + 0.9 * random_double!(rng));
+
+            if (center - Point3::new(4.0, 0.2, 0.0)).length() > 0.9 {
+                let mut center2 = center;
+                let sphere_material: Arc<dyn Material> = if choose_mat < 0.8 {
+                    // diffuse
+                    let albedo = Color::random(rng, 0.0, 1.0) * Color::random(rng, 0.0, 1.0);
+                    if bouncing {
+                        center2 += Vec3::new(0.0, random_double!(rng, 0.0, 0.5), 0.0);
+                    }
+                    Arc::new(Lambertian::new(albedo))
+                } else if choose_mat < 0.95 {
+                    // metal
+                    let albedo = Color::random(rng, 0.5, 1.0);
+                    let fuzz = random_double!(rng, 0.0, 0.5);
+                    Arc::new(Metal::new(albedo, fuzz))
+                } else {
+                    // glass
+                    Arc::new(Dielectric::new(1.5))
+                };
+                world.add(Arc::new(Sphere::moving(center, center2, 0.2, Some(sphere_material))));
+            }
+        }
+    }
+
+    let material1: Arc<dyn Material> = Arc::new(Dielectric::new(1.5));
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, 1.0, 0.0), 1.0, Some(material1))));
+    let material2: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.4, 0.2, 0.1)));
+    world.add(Arc::new(Sphere::new(Point3::new(-4.0, 1.0, 0.0), 1.0, Some(material2))));
+    let material3: Arc<dyn Material> = Arc::new(Metal::new(Color::new(0.7, 0.6, 0.5), 0.0));
+    world.add(Arc::new(Sphere::new(Point3::new(4.0, 1.0, 0.0), 1.0, Some(material3))));
+
+    // Testing every ray against all ~500 spheres is slow, so put them in a bounding volume
+    // hierarchy first.
+    let mut bvh = HittableList::new();
+    bvh.add(Arc::new(BvhNode::new(world)));
+    bvh
+}
+
+// Two big spheres, one above the other, cut out of the same 3D checker pattern.
+fn checkered_spheres() -> HittableList {
+    let checker: Arc<dyn Material> = Arc::new(Lambertian::from_texture(Arc::new(
+        CheckerTexture::from_colors(0.32, Color::new(0.2, 0.3, 0.1), Color::new(0.9, 0.9, 0.9)))));
+
+    let mut world = HittableList::new();
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, -10.0, 0.0), 10.0,
+                                   Some(Arc::clone(&checker)))));
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, 10.0, 0.0), 10.0, Some(checker))));
+    world
+}
+
+// A marble sphere sitting on a marble ground.
+fn perlin_spheres(rng: &mut SmallRng) -> HittableList {
+    let marble: Arc<dyn Material> = Arc::new(Lambertian::from_texture(Arc::new(
+        NoiseTexture::new(4.0, NoiseStyle::Marble, rng))));
+
+    let mut world = HittableList::new();
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0,
+                                   Some(Arc::clone(&marble)))));
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, 2.0, 0.0), 2.0, Some(marble))));
+    world
+}
+
+// Five quads making up an open box, each with a different color.
+fn quads() -> HittableList {
+    let quad = |q, u, v, albedo| -> Arc<Quad> {
+        Arc::new(Quad::new(q, u, v, Some(Arc::new(Lambertian::new(albedo)))))
+    };
+
+    let mut world = HittableList::new();
+    world.add(quad(Point3::new(-3.0, -2.0, 5.0), Vec3::new(0.0, 0.0, -4.0),
+                   Vec3::new(0.0, 4.0, 0.0), Color::new(1.0, 0.2, 0.2)));  // left red
+    world.add(quad(Point3::new(-2.0, -2.0, 0.0), Vec3::new(4.0, 0.0, 0.0),
+                   Vec3::new(0.0, 4.0, 0.0), Color::new(0.2, 1.0, 0.2)));  // back green
+    world.add(quad(Point3::new(3.0, -2.0, 1.0), Vec3::new(0.0, 0.0, 4.0),
+                   Vec3::new(0.0, 4.0, 0.0), Color::new(0.2, 0.2, 1.0)));  // right blue
+    world.add(quad(Point3::new(-2.0, 3.0, 1.0), Vec3::new(4.0, 0.0, 0.0),
+                   Vec3::new(0.0, 0.0, 4.0), Color::new(1.0, 0.5, 0.0)));  // upper orange
+    world.add(quad(Point3::new(-2.0, -3.0, 5.0), Vec3::new(4.0, 0.0, 0.0),
+                   Vec3::new(0.0, 0.0, -4.0), Color::new(0.2, 0.8, 0.8))); // lower teal
+    world
+}
+
+// Two spheres lit only by a spherical and a rectangular light, which are added to `lights`.
+fn simple_light(lights: &mut HittableList) -> HittableList {
+    let gray: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let light: Arc<dyn Material> = Arc::new(DiffuseLight::new(Color::new(4.0, 4.0, 4.0)));
+
+    let mut world = HittableList::new();
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0,
+                                   Some(Arc::clone(&gray)))));
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, 2.0, 0.0), 2.0, Some(gray))));
+    let sphere_light = Arc::new(Sphere::new(Point3::new(0.0, 7.0, 0.0), 2.0,
+                                            Some(Arc::clone(&light))));
+    let quad_light = Arc::new(Quad::new(Point3::new(3.0, 1.0, -2.0), Vec3::new(2.0, 0.0, 0.0),
+                                        Vec3::new(0.0, 2.0, 0.0), Some(light)));
+    lights.add(sphere_light.clone());
+    lights.add(quad_light.clone());
+    world.add(sphere_light);
+    world.add(quad_light);
+    world
+}
+
+// The classic Cornell box: a white room with a red and a green wall, lit by a light in the
+// ceiling, holding two rotated boxes. With `smoke`, the boxes are filled with black smoke and
+// white fog instead, under a bigger, dimmer light. The light is added to `lights`.
+fn cornell_box(smoke: bool, lights: &mut HittableList) -> HittableList {
+    let red: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.65, 0.05, 0.05)));
+    let white: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.73, 0.73, 0.73)));
+    let green: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.12, 0.45, 0.15)));
+    let emit = if smoke { 7.0 } else { 15.0 };
+    let light: Arc<dyn Material> = Arc::new(DiffuseLight::new(Color::new(emit, emit, emit)));
+
+    let mut world = HittableList::new();
+    let mut wall = |q: Point3, u: Vec3, v: Vec3, mat: &Arc<dyn Material>| {
+        world.add(Arc::new(Quad::new(q, u, v, Some(Arc::clone(mat)))));
+    };
+    wall(Point3::new(555.0, 0.0, 0.0), Vec3::new(0.0, 555.0, 0.0), Vec3::new(0.0, 0.0, 555.0),
+         &green);
+    wall(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 555.0, 0.0), Vec3::new(0.0, 0.0, 555.0),
+         &red);
+    wall(Point3::new(0.0, 0.0, 0.0), Vec3::new(555.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 555.0),
+         &white);
+    wall(Point3::new(555.0, 555.0, 555.0), Vec3::new(-555.0, 0.0, 0.0),
+         Vec3::new(0.0, 0.0, -555.0), &white);
+    wall(Point3::new(0.0, 0.0, 555.0), Vec3::new(555.0, 0.0, 0.0), Vec3::new(0.0, 555.0, 0.0),
+         &white);
+
+    let ceiling_light = if smoke {
+        Quad::new(Point3::new(113.0, 554.0, 127.0), Vec3::new(330.0, 0.0, 0.0),
+                  Vec3::new(0.0, 0.0, 305.0), Some(light))
+    } else {
+        Quad::new(Point3::new(343.0, 554.0, 332.0), Vec3::new(-130.0, 0.0, 0.0),
+                  Vec3::new(0.0, 0.0, -105.0), Some(light))
+    };
+    let ceiling_light = Arc::new(ceiling_light);
+    lights.add(ceiling_light.clone());
+    world.add(ceiling_light);
+
+    let mut rotated_box = |size: Vec3, degrees: f64, offset: Vec3, smoke_color: Color| {
+        let object = Arc::new(quad::Box::new(Point3::new(0.0, 0.0, 0.0), size,
+                                             Some(Arc::clone(&white))));
+        let to_world = Matrix4::translation(offset)
+            * Matrix4::rotation(Vec3::new(0.0, 1.0, 0.0), degrees);
+        let object = Arc::new(Transform::new(object, to_world));
+        if smoke {
+            let phase_function: Arc<dyn Material> = Arc::new(Isotropic::new(smoke_color));
+            world.add(Arc::new(ConstantMedium::new(object, 0.01, Some(phase_function))));
+        } else {
+            world.add(object);
+        }
+    };
+    rotated_box(Vec3::new(165.0, 330.0, 165.0), 15.0, Vec3::new(265.0, 0.0, 295.0),
+                Color::new(0.0, 0.0, 0.0));
+    rotated_box(Vec3::new(165.0, 165.0, 165.0), -18.0, Vec3::new(130.0, 0.0, 65.0),
+                Color::new(1.0, 1.0, 1.0));
+
+    world
+}
+
+fn to_vec3(v: [f64; 3]) -> Vec3 {
+    Vec3::new(v[0], v[1], v[2])
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDescription {
+    camera: CameraDescription,
+    background: Option<BackgroundDescription>,
+    #[serde(default)]
+    materials: HashMap<String, MaterialDescription>,
+    #[serde(default)]
+    objects: Vec<ObjectDescription>,
+}
+
+// A plain color, or a table for the other kinds of background.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BackgroundDescription {
+    Solid([f64; 3]),
+    Other(OtherBackgroundDescription),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum OtherBackgroundDescription {
+    Gradient { bottom: [f64; 3], top: [f64; 3] },
+    Environment { path: String },
+}
+
+impl BackgroundDescription {
+    fn build(&self, directory: &Path) -> Result<Background, SceneError> {
+        Ok(match self {
+            BackgroundDescription::Solid(color) => Background::Solid(to_vec3(*color)),
+            BackgroundDescription::Other(OtherBackgroundDescription::Gradient { bottom, top }) =>
+                Background::Gradient { bottom: to_vec3(*bottom), top: to_vec3(*top) },
+            BackgroundDescription::Other(OtherBackgroundDescription::Environment { path }) => {
+                let path = directory.join(path);
+                let image = Framebuffer::load(&path.to_string_lossy())
+                    .map_err(|err| SceneError::Environment { path, err })?;
+                Background::Environment(Arc::new(image))
+            }
+        })
+    }
+}
+
+// The image settings are required, the view settings fall back to the `Camera::new` defaults.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraDescription {
+    image_width: i32,
+    aspect_ratio: f64,
+    samples_per_pixel: i32,
+    max_depth: i32,
+    vfov: Option<f64>,
+    lookfrom: Option<[f64; 3]>,
+    lookat: Option<[f64; 3]>,
+    vup: Option<[f64; 3]>,
+    defocus_angle: Option<f64>,
+    focus_dist: Option<f64>,
+    shutter: Option<[f64; 2]>,
+}
+
+impl CameraDescription {
+    fn build(&self) -> Result<Camera, SceneError> {
+        for (setting, value) in [("image_width", self.image_width),
+                                 ("samples_per_pixel", self.samples_per_pixel),
+                                 ("max_depth", self.max_depth)] {
+            if value <= 0 {
+                return Err(SceneError::InvalidCamera { setting });
+            }
+        }
+
+        let mut camera = Camera::new(self.image_width, self.aspect_ratio, self.samples_per_pixel,
+                                     self.max_depth);
+        if let Some(vfov) = self.vfov {
+            camera.vfov = vfov;
+        }
+        if let Some(lookfrom) = self.lookfrom {
+            camera.lookfrom = to_vec3(lookfrom);
+        }
+        if let Some(lookat) = self.lookat {
+            camera.lookat = to_vec3(lookat);
+        }
+        if let Some(vup) = self.vup {
+            camera.vup = to_vec3(vup);
+        }
+        if let Some(defocus_angle) = self.defocus_angle {
+            camera.defocus_angle = defocus_angle;
+        }
+        if let Some(focus_dist) = self.focus_dist {
+            camera.focus_dist = focus_dist;
+        }
+        if let Some([open, close]) = self.shutter {
+            camera.shutter = Interval::new(open, close);
+        }
+
+        // Anything else leaves the camera without a height, a focus plane or a way to orient
+        // its view, and an aspect ratio of 0 asks for an image infinitely tall.
+        for (setting, value) in [("aspect_ratio", self.aspect_ratio),
+                                 ("focus_dist", camera.focus_dist)] {
+            if !(value.is_finite() && value > 0.0) {
+                return Err(SceneError::InvalidCamera { setting });
+            }
+        }
+        if !(camera.vfov > 0.0 && camera.vfov < 180.0) {
+            return Err(SceneError::InvalidCamera { setting: "vfov" });
+        }
+        let view_direction = camera.lookat - camera.lookfrom;
+        let view_distance = view_direction.length();
+        if !(view_distance.is_finite() && view_distance > 0.0) {
+            return Err(SceneError::InvalidCamera { setting: "lookat" });
+        }
+        // The sine of the angle between them, NaN if `vup` is 0.
+        let sine = cross(unit_vector(camera.vup), view_direction / view_distance).length();
+        if sine.is_nan() || sine <= 1e-9 {
+            return Err(SceneError::InvalidCamera { setting: "vup" });
+        }
+        Ok(camera)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum MaterialDescription {
+    Lambertian { albedo: TextureDescription },
+    Metal { albedo: TextureDescription, #[serde(default)] fuzz: f64 },
+    Dielectric { index_of_refraction: f64 },
+    DiffuseLight { emit: TextureDescription },
+    Isotropic { albedo: TextureDescription },
+}
+
+impl MaterialDescription {
+    fn build(&self, directory: &Path, rng: &mut SmallRng) -> Result<Arc<dyn Material>, SceneError> {
+        Ok(match self {
+            MaterialDescription::Lambertian { albedo } =>
+                Arc::new(Lambertian::from_texture(albedo.build(directory, rng)?)),
+            MaterialDescription::Metal { albedo, fuzz } =>
+                Arc::new(Metal::from_texture(albedo.build(directory, rng)?, *fuzz)),
+            MaterialDescription::Dielectric { index_of_refraction } =>
+                Arc::new(Dielectric::new(*index_of_refraction)),
+            MaterialDescription::DiffuseLight { emit } =>
+                Arc::new(DiffuseLight::from_texture(emit.build(directory, rng)?)),
+            MaterialDescription::Isotropic { albedo } =>
+                Arc::new(Isotropic::from_texture(albedo.build(directory, rng)?)),
+        })
+    }
+}
+
+// A plain color, or a table for the other kinds of texture.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TextureDescription {
+    Solid([f64; 3]),
+    Other(OtherTextureDescription),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum OtherTextureDescription {
+    Checker { scale: f64, even: Box<TextureDescription>, odd: Box<TextureDescription> },
+    Image { path: String },
+    Noise { scale: f64, #[serde(default)] style: NoiseStyleDescription },
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+enum NoiseStyleDescription {
+    Smooth,
+    Turbulence,
+    #[default]
+    Marble,
+}
+
+impl TextureDescription {
+    fn build(&self, directory: &Path, rng: &mut SmallRng) -> Result<Arc<dyn Texture>, SceneError> {
+        Ok(match self {
+            TextureDescription::Solid(color) => Arc::new(SolidColor::new(to_vec3(*color))),
+            TextureDescription::Other(OtherTextureDescription::Checker { scale, even, odd }) =>
+                Arc::new(CheckerTexture::new(*scale, even.build(directory, rng)?,
+                                             odd.build(directory, rng)?)),
+            TextureDescription::Other(OtherTextureDescription::Image { path }) => {
+                let path = directory.join(path);
+                let texture = ImageTexture::load(&path.to_string_lossy())
+                    .map_err(|err| SceneError::Texture { path, err })?;
+                Arc::new(texture)
+            }
+            TextureDescription::Other(OtherTextureDescription::Noise { scale, style }) => {
+                let style = match style {
+                    NoiseStyleDescription::Smooth => NoiseStyle::Smooth,
+                    NoiseStyleDescription::Turbulence => NoiseStyle::Turbulence,
+                    NoiseStyleDescription::Marble => NoiseStyle::Marble,
+                };
+                Arc::new(NoiseTexture::new(*scale, style, rng))
+            }
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum ObjectDescription {
+    Sphere {
+        center: [f64; 3],
+        center2: Option<[f64; 3]>,
+        radius: f64,
+        material: Option<String>,
+        transform: Option<TransformDescription>,
+        density: Option<f64>,
+    },
+    Plane {
+        point: [f64; 3],
+        normal: [f64; 3],
+        material: Option<String>,
+        transform: Option<TransformDescription>,
+        density: Option<f64>,
+    },
+    Quad {
+        q: [f64; 3],
+        u: [f64; 3],
+        v: [f64; 3],
+        material: Option<String>,
+        transform: Option<TransformDescription>,
+        density: Option<f64>,
+    },
+    Disk {
+        center: [f64; 3],
+        normal: [f64; 3],
+        radius: f64,
+        material: Option<String>,
+        transform: Option<TransformDescription>,
+        density: Option<f64>,
+    },
+    Box {
+        a: [f64; 3],
+        b: [f64; 3],
+        material: Option<String>,
+        transform: Option<TransformDescription>,
+        density: Option<f64>,
+    },
+    Mesh {
+        path: String,
+        material: Option<String>,
+        transform: Option<TransformDescription>,
+        density: Option<f64>,
+    },
+}
+
+impl ObjectDescription {
+    fn material(&self) -> Option<&str> {
+        match self {
+            ObjectDescription::Sphere { material, .. }
+            | ObjectDescription::Plane { material, .. }
+            | ObjectDescription::Quad { material, .. }
+            | ObjectDescription::Disk { material, .. }
+            | ObjectDescription::Box { material, .. }
+            | ObjectDescription::Mesh { material, .. } => material.as_deref(),
+        }
+    }
+
+    fn transform(&self) -> Option<&TransformDescription> {
+        match self {
+            ObjectDescription::Sphere { transform, .. }
+            | ObjectDescription::Plane { transform, .. }
+            | ObjectDescription::Quad { transform, .. }
+            | ObjectDescription::Disk { transform, .. }
+            | ObjectDescription::Box { transform, .. }
+            | ObjectDescription::Mesh { transform, .. } => transform.as_ref(),
+        }
+    }
+
+    fn density(&self) -> Option<f64> {
+        match self {
+            ObjectDescription::Sphere { density, .. }
+            | ObjectDescription::Plane { density, .. }
+            | ObjectDescription::Quad { density, .. }
+            | ObjectDescription::Disk { density, .. }
+            | ObjectDescription::Box { density, .. }
+            | ObjectDescription::Mesh { density, .. } => *density,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TransformDescription {
+    scale: Option<ScaleDescription>,
+    rotate: Option<[f64; 3]>,
+    translate: Option<[f64; 3]>,
+}
+
+// Either one factor for all three axes, or one per axis.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ScaleDescription {
+    Uniform(f64),
+    PerAxis([f64; 3]),
+}
+
+impl TransformDescription {
+    fn matrix(&self) -> Matrix4 {
+        let mut matrix = Matrix4::IDENTITY;
+        if let Some(scale) = &self.scale {
+            let factors = match scale {
+                ScaleDescription::Uniform(factor) => Vec3::new(*factor, *factor, *factor),
+                ScaleDescription::PerAxis(factors) => to_vec3(*factors),
+            };
+            matrix = Matrix4::scaling(factors) * matrix;
+        }
+        if let Some([x, y, z]) = self.rotate {
+            matrix = Matrix4::rotation(Vec3::new(1.0, 0.0, 0.0), x) * matrix;
+            matrix = Matrix4::rotation(Vec3::new(0.0, 1.0, 0.0), y) * matrix;
+            matrix = Matrix4::rotation(Vec3::new(0.0, 0.0, 1.0), z) * matrix;
+        }
+        if let Some(translate) = self.translate {
+            matrix = Matrix4::translation(to_vec3(translate)) * matrix;
+        }
+        matrix
+    }
+}
@@ -1,19 +1,52 @@
-use std::rc::Rc;
-use crate::hittable::{HitRecord, Hittable};
+use std::f64::consts::PI;
+use std::sync::Arc;
+use crate::aabb::Aabb;
+use crate::hittable::{object_id, HitRecord, Hittable};
 use crate::interval::Interval;
 use crate::material::Material;
+use crate::onb::Onb;
 use crate::ray::Ray;
-use crate::vec3::{dot, Point3};
+use crate::utils::{random_double, SmallRng};
+use crate::vec3::{dot, Point3, random_unit_vector, Vec3};
 
-pub(crate) struct Sphere {
-    center: Point3,
+pub struct Sphere {
+    // The center moves along this ray over time: at time 0 it's at the ray's origin and at time 1
+    // it's one direction further. A stationary sphere has a zero direction.
+    center: Ray,
     radius: f64,
-    mat: Option<Rc<dyn Material>>,
+    mat: Option<Arc<dyn Material>>,
+    bbox: Aabb,
 }
 
 impl Sphere {
-    pub(crate) fn new(center: Point3, radius: f64, mat: Option<Rc<dyn Material>>) -> Sphere {
-        Sphere { center, radius, mat }
+    pub fn new(center: Point3, radius: f64, mat: Option<Arc<dyn Material>>) -> Sphere {
+        let rvec = Vec3::new(radius, radius, radius);
+        let bbox = Aabb::from_points(center - rvec, center + rvec);
+        Sphere { center: Ray::new(center, Vec3::default()), radius, mat, bbox }
+    }
+
+    // https://raytracing.github.io/books/RayTracingTheNextWeek.html#motionblur
+    // A sphere moving in a straight line from `center1` at time 0 to `center2` at time 1. Its
+    // bounding box has to hold it over the whole of that, so it's the box around both ends.
+    pub fn moving(center1: Point3, center2: Point3, radius: f64, mat: Option<Arc<dyn Material>>)
+                  -> Sphere {
+        let rvec = Vec3::new(radius, radius, radius);
+        let box1 = Aabb::from_points(center1 - rvec, center1 + rvec);
+        let box2 = Aabb::from_points(center2 - rvec, center2 + rvec);
+        Sphere { center: Ray::new(center1, center2 - center1), radius, mat,
+                 bbox: Aabb::enclosing(&box1, &box2) }
+    }
+
+    // https://raytracing.github.io/books/RayTracingTheNextWeek.html#texturemapping/texturecoordinatesforspheres
+    // Maps a point p on the unit sphere around the origin to (u, v) in [0, 1]:
+    //     u: the angle around the y axis, from x = -1 through z = +1, x = +1, z = -1 and back,
+    //     v: the angle from y = -1 up to y = +1.
+    // With the polar angle theta measured up from -y and the azimuth phi around y from -x,
+    // u = phi / 2pi and v = theta / pi.
+    fn get_sphere_uv(p: Point3) -> (f64, f64) {
+        let theta = f64::acos(-p.y);
+        let phi = f64::atan2(-p.z, p.x) + PI;
+        (phi / (2.0 * PI), theta / PI)
     }
 }
 
@@ -26,7 +59,8 @@ impl Hittable for Sphere {
     /// No, we may hit something closer to the origin, so we don't have to render it.
     /// Regardless, we'll start with a simple solution.
     fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
-        let delta = r.origin - self.center;
+        let current_center = self.center.at(r.time);
+        let delta = r.origin - current_center;
         let a = r.direction.length_squared();
         let half_b = dot(delta, r.direction);
         let c = delta.length_squared() - self.radius * self.radius;
@@ -48,14 +82,61 @@ impl Hittable for Sphere {
 
         rec.t = root;
         rec.p = Some(r.at(root));
-        let outward_normal = (rec.p.unwrap() - self.center) / self.radius;
+        let outward_normal = (rec.p.unwrap() - current_center) / self.radius;
         rec.set_face_normal(r, outward_normal);
-        if self.mat.is_none() {
-            rec.mat = None
-        } else {
-            rec.mat = Some(Rc::clone(&self.mat.as_ref().unwrap()));
-        }
+        (rec.u, rec.v) = Self::get_sphere_uv(outward_normal);
+        rec.mat = self.mat.as_ref().map(Arc::clone);
+        rec.object_id = object_id(self);
 
         true
     }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    // https://raytracing.github.io/books/RayTracingTheRestOfYourLife.html#cleaninguppdfmanagement/samplingasphereobject
+    // Seen from outside, a sphere covers a cone of directions around the one to its center, with
+    // cos(theta_max) = sqrt(1 - R^2 / d^2) at distance d. Sampling directions uniformly within
+    // that cone gives a density of one over its solid angle, 2pi(1 - cos(theta_max)).
+    //
+    // From inside, every direction hits it, so any direction is as good as another. Moving
+    // spheres are sampled where they are at time 0.
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let mut rec = HitRecord::default();
+        if !self.hit(&Ray::new(origin, direction), Interval::new(0.001, f64::INFINITY), &mut rec) {
+            return 0.0;
+        }
+
+        let distance_squared = (self.center.at(0.0) - origin).length_squared();
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared {
+            return 1.0 / (4.0 * PI);
+        }
+        let cos_theta_max = f64::sqrt(1.0 - radius_squared / distance_squared);
+        let solid_angle = 2.0 * PI * (1.0 - cos_theta_max);
+
+        1.0 / solid_angle
+    }
+
+    fn random(&self, origin: Point3, rng: &mut SmallRng) -> Vec3 {
+        let direction = self.center.at(0.0) - origin;
+        let distance_squared = direction.length_squared();
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared {
+            return random_unit_vector(rng);
+        }
+
+        // A random direction in the cone around +z, turned to point at the center. The cone's
+        // solid angle is proportional to 1 - cos(theta_max), so z = cos(theta) is uniform
+        // between cos(theta_max) and 1.
+        let r1 = random_double!(rng);
+        let r2 = random_double!(rng);
+        let z = 1.0 + r2 * (f64::sqrt(1.0 - radius_squared / distance_squared) - 1.0);
+        let phi = 2.0 * PI * r1;
+        let x = f64::cos(phi) * f64::sqrt(1.0 - z * z);
+        let y = f64::sin(phi) * f64::sqrt(1.0 - z * z);
+
+        Onb::new(direction).transform(Vec3::new(x, y, z))
+    }
 }
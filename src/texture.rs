@@ -0,0 +1,139 @@
+use std::io;
+use std::sync::Arc;
+use crate::interval::Interval;
+use crate::output::Framebuffer;
+use crate::perlin::Perlin;
+use crate::utils::SmallRng;
+use crate::vec3::{Color, Point3};
+
+// https://raytracing.github.io/books/RayTracingTheNextWeek.html#texturemapping
+// A texture makes a surface's color vary over it. It's looked up with the surface coordinates
+// (u, v) of the hit point, which suits images, or the hit point p itself, which suits solid
+// textures that fill space like a block of marble the object is carved out of.
+pub trait Texture: Send + Sync {
+    fn value(&self, u: f64, v: f64, p: Point3) -> Color;
+}
+
+// The same color everywhere, what materials used before they took textures.
+pub struct SolidColor {
+    albedo: Color,
+}
+
+impl SolidColor {
+    pub fn new(albedo: Color) -> Self {
+        Self { albedo }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _p: Point3) -> Color {
+        self.albedo
+    }
+}
+
+// A 3D checker pattern of cubes `scale` wide, alternating between two textures. Being solid, it
+// doesn't depend on how the surface is parameterized, and a surface cutting through it shows
+// the checkers wherever it goes.
+pub struct CheckerTexture {
+    inv_scale: f64,
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+}
+
+impl CheckerTexture {
+    pub fn new(scale: f64, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> Self {
+        Self { inv_scale: 1.0 / scale, even, odd }
+    }
+
+    pub fn from_colors(scale: f64, c1: Color, c2: Color) -> Self {
+        Self::new(scale, Arc::new(SolidColor::new(c1)), Arc::new(SolidColor::new(c2)))
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: f64, v: f64, p: Point3) -> Color {
+        let x = (self.inv_scale * p.x).floor() as i64;
+        let y = (self.inv_scale * p.y).floor() as i64;
+        let z = (self.inv_scale * p.z).floor() as i64;
+
+        if (x + y + z) % 2 == 0 {
+            self.even.value(u, v, p)
+        } else {
+            self.odd.value(u, v, p)
+        }
+    }
+}
+
+// An image wrapped over the surface by its (u, v) coordinates: u goes left to right across the
+// image and v bottom to top. Pixels are looked up nearest neighbour.
+pub struct ImageTexture {
+    image: Arc<Framebuffer>,
+}
+
+impl ImageTexture {
+    pub fn new(image: Arc<Framebuffer>) -> Self {
+        Self { image }
+    }
+
+    /// Loads the image at `file_path`, in any format `Framebuffer::load` reads.
+    pub fn load(file_path: &str) -> io::Result<Self> {
+        Ok(Self::new(Arc::new(Framebuffer::load(file_path)?)))
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: Point3) -> Color {
+        // With no texture data, return solid cyan as a debugging aid.
+        if self.image.width() == 0 || self.image.height() == 0 {
+            return Color::new(0.0, 1.0, 1.0);
+        }
+
+        // Clamp the coordinates to [0, 1], and flip v to image rows, which go top to bottom.
+        let unit = Interval::new(0.0, 1.0);
+        let u = unit.clamp(u);
+        let v = 1.0 - unit.clamp(v);
+
+        let i = ((u * self.image.width() as f64) as usize).min(self.image.width() - 1);
+        let j = ((v * self.image.height() as f64) as usize).min(self.image.height() - 1);
+        self.image.pixel(i, j)
+    }
+}
+
+// The patterns `NoiseTexture` makes out of Perlin noise.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NoiseStyle {
+    // Plain noise: soft blotches.
+    Smooth,
+    // Turbulence: several octaves of noise summed together, like a camouflage net.
+    Turbulence,
+    // Stripes of a sine wave along z, with their phase pushed around by turbulence, which
+    // gives veins like marble.
+    Marble,
+}
+
+// A solid gray texture made out of Perlin noise. `scale` is the frequency of the noise: the
+// bigger it is, the finer the pattern.
+pub struct NoiseTexture {
+    noise: Perlin,
+    scale: f64,
+    style: NoiseStyle,
+}
+
+impl NoiseTexture {
+    pub fn new(scale: f64, style: NoiseStyle, rng: &mut SmallRng) -> Self {
+        Self { noise: Perlin::new(rng), scale, style }
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: f64, _v: f64, p: Point3) -> Color {
+        let gray = match self.style {
+            // Noise is in [-1, 1], so map it to [0, 1].
+            NoiseStyle::Smooth => 0.5 * (1.0 + self.noise.noise(self.scale * p)),
+            NoiseStyle::Turbulence => self.noise.turb(self.scale * p, 7),
+            NoiseStyle::Marble => 0.5 * (1.0 + f64::sin(self.scale * p.z
+                                                         + 10.0 * self.noise.turb(p, 7))),
+        };
+        Color::new(gray, gray, gray)
+    }
+}
@@ -0,0 +1,233 @@
+use std::ops::Mul;
+use std::sync::Arc;
+use crate::aabb::Aabb;
+use crate::hittable::{object_id, HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::ray::Ray;
+use crate::utils::{degrees_to_radians, SmallRng};
+use crate::vec3::{Point3, unit_vector, Vec3};
+
+/// A 4x4 affine transformation matrix, acting on column vectors: `a * b` applies `b` first.
+///
+/// Points are extended with a fourth coordinate of 1 and vectors with 0, so translations move
+/// points but leave directions alone. Only affine matrices are supported, so the bottom row is
+/// always [0, 0, 0, 1].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Matrix4 {
+    pub m: [[f64; 4]; 4],
+}
+
+impl Matrix4 {
+    pub const IDENTITY: Matrix4 = Matrix4 {
+        m: [[1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0]],
+    };
+
+    pub fn translation(offset: Vec3) -> Matrix4 {
+        let mut matrix = Self::IDENTITY;
+        for i in 0..3 {
+            matrix.m[i][3] = offset[i];
+        }
+        matrix
+    }
+
+    pub fn scaling(factors: Vec3) -> Matrix4 {
+        let mut matrix = Self::IDENTITY;
+        for i in 0..3 {
+            matrix.m[i][i] = factors[i];
+        }
+        matrix
+    }
+
+    // Rotation by `degrees` counter-clockwise around `axis`, looking down the axis towards the
+    // origin (Rodrigues' rotation formula).
+    pub fn rotation(axis: Vec3, degrees: f64) -> Matrix4 {
+        let k = unit_vector(axis);
+        let (sin, cos) = degrees_to_radians(degrees).sin_cos();
+        let t = 1.0 - cos;
+        Matrix4 {
+            m: [[t * k.x * k.x + cos, t * k.x * k.y - sin * k.z, t * k.x * k.z + sin * k.y, 0.0],
+                [t * k.x * k.y + sin * k.z, t * k.y * k.y + cos, t * k.y * k.z - sin * k.x, 0.0],
+                [t * k.x * k.z - sin * k.y, t * k.y * k.z + sin * k.x, t * k.z * k.z + cos, 0.0],
+                [0.0, 0.0, 0.0, 1.0]],
+        }
+    }
+
+    pub fn transform_point(&self, p: Point3) -> Point3 {
+        self.transform_vector(p) + Vec3::new(self.m[0][3], self.m[1][3], self.m[2][3])
+    }
+
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        let row = |i: usize| self.m[i][0] * v.x + self.m[i][1] * v.y + self.m[i][2] * v.z;
+        Vec3::new(row(0), row(1), row(2))
+    }
+
+    pub fn transpose(&self) -> Matrix4 {
+        let mut matrix = *self;
+        for i in 0..4 {
+            for j in 0..4 {
+                matrix.m[i][j] = self.m[j][i];
+            }
+        }
+        matrix
+    }
+
+    // The cofactor of the 3x3 linear part at row i, column j. Indices wrap around, which gets the
+    // signs right without a checkerboard of minus signs.
+    fn cofactor(&self, i: usize, j: usize) -> f64 {
+        let a = |i: usize, j: usize| self.m[i % 3][j % 3];
+        a(i + 1, j + 1) * a(i + 2, j + 2) - a(i + 1, j + 2) * a(i + 2, j + 1)
+    }
+
+    // The determinant of the 3x3 linear part: how much the matrix scales volumes.
+    pub fn determinant(&self) -> f64 {
+        (0..3).map(|j| self.m[0][j] * self.cofactor(0, j)).sum()
+    }
+
+    // For an affine matrix [A t], the inverse is [A^-1 -A^-1 t]. A^-1 is the transposed matrix
+    // of cofactors divided by the determinant, or doesn't exist when the determinant is zero,
+    // e.g. after scaling by zero.
+    pub fn inverse(&self) -> Option<Matrix4> {
+        let det = self.determinant();
+        if det.abs() < 1e-12 {
+            return None;
+        }
+
+        let mut inverse = Self::IDENTITY;
+        for i in 0..3 {
+            for j in 0..3 {
+                inverse.m[i][j] = self.cofactor(j, i) / det;
+            }
+        }
+        let translation = -inverse.transform_vector(Vec3::new(self.m[0][3], self.m[1][3],
+                                                              self.m[2][3]));
+        for i in 0..3 {
+            inverse.m[i][3] = translation[i];
+        }
+        Some(inverse)
+    }
+}
+
+impl Mul for Matrix4 {
+    type Output = Matrix4;
+
+    fn mul(self, rhs: Matrix4) -> Matrix4 {
+        let mut matrix = Matrix4 { m: [[0.0; 4]; 4] };
+        for i in 0..4 {
+            for j in 0..4 {
+                matrix.m[i][j] = (0..4).map(|k| self.m[i][k] * rhs.m[k][j]).sum();
+            }
+        }
+        matrix
+    }
+}
+
+/// An instance of another hittable, moved, rotated and scaled by an affine matrix.
+///
+/// The object is shared, not copied, so one mesh can be placed all over a scene for the cost of
+/// a matrix per instance.
+pub struct Transform {
+    object: Arc<dyn Hittable>,
+    // Object space to world space.
+    to_world: Matrix4,
+    // World space to object space.
+    to_object: Matrix4,
+    // Object space normals to world space, the transpose of `to_object`.
+    normal_to_world: Matrix4,
+    bbox: Aabb,
+}
+
+impl Transform {
+    /// Wraps `object` in the transformation `to_world`.
+    ///
+    /// Panics if the matrix can't be inverted.
+    pub fn new(object: Arc<dyn Hittable>, to_world: Matrix4) -> Transform {
+        let to_object = to_world.inverse().expect("Unable to invert the transform matrix");
+        let normal_to_world = to_object.transpose();
+        let bbox = transform_box(&object.bounding_box(), &to_world);
+        Transform { object, to_world, to_object, normal_to_world, bbox }
+    }
+}
+
+impl Hittable for Transform {
+    // Rather than transforming the object, we move the ray into object space, intersect it
+    // there, and move the hit back out to world space.
+    //
+    // The ray's direction isn't normalized after the transform, so a point at distance t along
+    // the object space ray is the same point as at t along the world space ray, and `ray_t` and
+    // `rec.t` mean the same in both spaces.
+    //
+    // Normals are the one thing that don't transform like the vectors they are: a scaled
+    // surface's normals get squashed the other way. They transform by the inverse transpose,
+    // which also keeps their dot product with the ray direction, so `front_face` stays correct.
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let object_r = Ray::with_time(self.to_object.transform_point(r.origin),
+                                      self.to_object.transform_vector(r.direction), r.time);
+
+        if !self.object.hit(&object_r, ray_t, rec) {
+            return false;
+        }
+
+        rec.p = Some(self.to_world.transform_point(rec.p.unwrap()));
+        let normal = self.normal_to_world.transform_vector(rec.normal.unwrap());
+        rec.normal = Some(unit_vector(normal));
+        // Instances of the same object are different objects.
+        rec.object_id = object_id(self);
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    // Directions map between the two spaces like any other vector, but densities per unit of
+    // solid angle don't: scaling squashes some directions together and spreads others apart.
+    // For a linear map M taking a unit direction w to M w / |M w|, solid angles change by a
+    // factor of |det M| / |M w|^3, so that's what the object space density gets multiplied by.
+    // Rotations and uniform scaling leave it at 1.
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let object_origin = self.to_object.transform_point(origin);
+        let object_direction = self.to_object.transform_vector(direction);
+        let object_pdf = self.object.pdf_value(object_origin, object_direction);
+        if object_pdf == 0.0 {
+            return 0.0;
+        }
+
+        let stretch = self.to_object.transform_vector(unit_vector(direction)).length();
+        object_pdf * self.to_object.determinant().abs() / stretch.powi(3)
+    }
+
+    fn random(&self, origin: Point3, rng: &mut SmallRng) -> Vec3 {
+        let object_origin = self.to_object.transform_point(origin);
+        self.to_world.transform_vector(self.object.random(object_origin, rng))
+    }
+}
+
+// The box around the eight transformed corners of `bbox`.
+//
+// Planes have infinite boxes, and infinity times a zero matrix entry is NaN, so zero entries are
+// skipped instead of multiplied out. A corner where infinities of opposite signs cancel out is
+// still NaN, but `f64::min` and `f64::max` ignore NaNs, and other corners give the right bounds.
+fn transform_box(bbox: &Aabb, matrix: &Matrix4) -> Aabb {
+    let mut min = Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+    let mut max = Point3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+    for corner in 0..8 {
+        let p = [
+            if corner & 1 == 0 { bbox.x.min } else { bbox.x.max },
+            if corner & 2 == 0 { bbox.y.min } else { bbox.y.max },
+            if corner & 4 == 0 { bbox.z.min } else { bbox.z.max },
+        ];
+        for i in 0..3 {
+            let coordinate = (0..3)
+                .filter(|&j| matrix.m[i][j] != 0.0)
+                .map(|j| matrix.m[i][j] * p[j])
+                .sum::<f64>() + matrix.m[i][3];
+            min[i] = f64::min(min[i], coordinate);
+            max[i] = f64::max(max[i], coordinate);
+        }
+    }
+    Aabb::from_points(min, max)
+}
@@ -0,0 +1,123 @@
+use std::sync::Arc;
+use crate::aabb::Aabb;
+use crate::hittable::{self, object_id, HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::utils::{random_double, SmallRng};
+use crate::vec3::{cross, dot, Point3, unit_vector, Vec3};
+
+/// A triangle given by its three vertices in counter-clockwise order, the usual winding for
+/// triangle meshes: looking at the front face, the vertices go around counter-clockwise.
+///
+/// Vertex normals, if set, are interpolated across the triangle so a mesh of flat triangles
+/// shades like the smooth surface it approximates. Vertex UVs are interpolated the same way into
+/// the hit record's texture coordinates, otherwise those are the barycentric coordinates.
+pub struct Triangle {
+    vertices: [Point3; 3],
+    normals: Option<[Vec3; 3]>,
+    uvs: Option<[(f64, f64); 3]>,
+    mat: Option<Arc<dyn Material>>,
+    bbox: Aabb,
+}
+
+impl Triangle {
+    pub fn new(a: Point3, b: Point3, c: Point3, mat: Option<Arc<dyn Material>>) -> Triangle {
+        let bbox = Aabb::enclosing(&Aabb::from_points(a, b), &Aabb::from_points(a, c));
+        Triangle { vertices: [a, b, c], normals: None, uvs: None, mat, bbox }
+    }
+
+    pub fn with_normals(mut self, normals: [Vec3; 3]) -> Triangle {
+        self.normals = Some(normals.map(unit_vector));
+        self
+    }
+
+    pub fn with_uvs(mut self, uvs: [(f64, f64); 3]) -> Triangle {
+        self.uvs = Some(uvs);
+        self
+    }
+}
+
+impl Hittable for Triangle {
+    // Möller–Trumbore: write the hit point in barycentric coordinates,
+    //     P = A + u(B - A) + v(C - A)
+    // and set it equal to the ray, A + u*e1 + v*e2 = O + t*D. That's a 3x3 linear system in
+    // t, u and v, which Cramer's rule solves with a handful of cross and dot products. The ray
+    // hits the triangle when u >= 0, v >= 0 and u + v <= 1.
+    // https://en.wikipedia.org/wiki/M%C3%B6ller%E2%80%93Trumbore_intersection_algorithm
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let [a, b, c] = self.vertices;
+        let e1 = b - a;
+        let e2 = c - a;
+
+        let pvec = cross(r.direction, e2);
+        let det = dot(e1, pvec);
+        // The ray is parallel to the triangle's plane.
+        if det.abs() < 1e-12 {
+            return false;
+        }
+        let inv_det = 1.0 / det;
+
+        let tvec = r.origin - a;
+        let u = dot(tvec, pvec) * inv_det;
+        if !(0.0..=1.0).contains(&u) {
+            return false;
+        }
+
+        let qvec = cross(tvec, e1);
+        let v = dot(r.direction, qvec) * inv_det;
+        if v < 0.0 || u + v > 1.0 {
+            return false;
+        }
+
+        let t = dot(e2, qvec) * inv_det;
+        if !ray_t.surrounds(t) {
+            return false;
+        }
+
+        rec.t = t;
+        rec.p = Some(r.at(t));
+        // Which side was hit depends on the true surface, so work that out from the geometric
+        // normal, then swap in the interpolated normal turned to the same side.
+        let geometric_normal = unit_vector(cross(e1, e2));
+        rec.set_face_normal(r, geometric_normal);
+        if let Some([na, nb, nc]) = self.normals {
+            let mut normal = unit_vector((1.0 - u - v) * na + u * nb + v * nc);
+            if dot(normal, geometric_normal) < 0.0 {
+                normal = -normal;
+            }
+            rec.normal = Some(if rec.front_face.unwrap() { normal } else { -normal });
+        }
+        (rec.u, rec.v) = match self.uvs {
+            Some([uva, uvb, uvc]) => ((1.0 - u - v) * uva.0 + u * uvb.0 + v * uvc.0,
+                                      (1.0 - u - v) * uva.1 + u * uvb.1 + v * uvc.1),
+            None => (u, v),
+        };
+        rec.mat = self.mat.as_ref().map(Arc::clone);
+        rec.object_id = object_id(self);
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let [a, b, c] = self.vertices;
+        let area = 0.5 * cross(b - a, c - a).length();
+        hittable::planar_pdf_value(self, area, origin, direction)
+    }
+
+    // A uniformly distributed point on the parallelogram spanned by two edges, folded back into
+    // the triangle when it lands in the other half.
+    fn random(&self, origin: Point3, rng: &mut SmallRng) -> Vec3 {
+        let [a, b, c] = self.vertices;
+        let (mut r1, mut r2) = (random_double!(rng), random_double!(rng));
+        if r1 + r2 > 1.0 {
+            (r1, r2) = (1.0 - r1, 1.0 - r2);
+        }
+        let p = a + r1 * (b - a) + r2 * (c - a);
+        p - origin
+    }
+}
@@ -1,18 +1,52 @@
-pub(crate) fn degrees_to_radians(degrees: f64) -> f64 {
+use rand::SeedableRng;
+
+// The random number generator threaded through everything that samples. `SmallRng` is much
+// cheaper than a cryptographically secure generator and we only need statistically decent
+// numbers. Passing it around explicitly, rather than reaching for a global, is what makes a
+// render repeatable: the same seed always produces the same image.
+pub use rand::rngs::SmallRng;
+
+pub fn degrees_to_radians(degrees: f64) -> f64 {
     degrees * std::f64::consts::PI / 180.0
 }
+
+/// Returns a generator seeded with `seed`, or from system entropy if there's no seed, in which
+/// case every run is different.
+pub fn new_rng(seed: Option<u64>) -> SmallRng {
+    match seed {
+        Some(seed) => SmallRng::seed_from_u64(seed),
+        None => SmallRng::from_entropy(),
+    }
+}
+
+/// Derives the seed of the `index`th independent stream (e.g. one per pixel) of a render seeded
+/// with `seed`.
+///
+/// Work is split across threads in whatever order they get to it, so instead of sharing one
+/// generator each stream gets its own, seeded only by its position. Both values are scrambled with
+/// SplitMix64 so that neighbouring streams, or neighbouring seeds, don't get related sequences.
+pub fn derive_seed(seed: u64, index: u64) -> u64 {
+    splitmix64(splitmix64(seed) ^ index)
+}
+
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
 macro_rules! random_double {
-    () => {{
-        use rand::{Rng, thread_rng};
-        let num : f64 = thread_rng().gen();
+    ($rng:expr) => {{
+        use rand::Rng;
+        let num : f64 = $rng.gen();
         num
     }};
-    ($min:expr, $max:expr) => {{
-        use rand::{Rng, thread_rng};
-        let num : f64 = thread_rng().gen_range($min..$max);
+    ($rng:expr, $min:expr, $max:expr) => {{
+        use rand::Rng;
+        let num : f64 = $rng.gen_range($min..$max);
         num
     }};
 }
 
-use rand::Rng;
-pub(crate) use random_double;
\ No newline at end of file
+pub(crate) use random_double;
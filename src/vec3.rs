@@ -1,42 +1,39 @@
-use crate::utils::random_double;
-
-#[derive(Clone, Copy, Debug)]
-pub(crate) struct Vec3 {
-    pub(crate) x: f64,
-    pub(crate) y: f64,
-    pub(crate) z: f64,
+use std::f64::consts::PI;
+use crate::utils::{random_double, SmallRng};
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Vec3 {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
 }
 
-pub(crate) type Color = Vec3;
-pub(crate) type Point3 = Vec3;
+pub type Color = Vec3;
+pub type Point3 = Vec3;
 
 impl Vec3 {
-    pub(crate) fn new(x: f64, y: f64, z: f64) -> Self {
+    pub fn new(x: f64, y: f64, z: f64) -> Self {
         Self { x, y, z }
     }
 
-    pub(crate) fn default() -> Self {
-        Self { x: 0.0, y: 0.0, z: 0.0 }
-    }
-
     //
-    pub(crate) fn random(min: f64, max: f64) -> Self {
+    pub fn random(rng: &mut SmallRng, min: f64, max: f64) -> Self {
         Self {
-            x: random_double!(min, max),
-            y: random_double!(min, max),
-            z: random_double!(min, max),
+            x: random_double!(rng, min, max),
+            y: random_double!(rng, min, max),
+            z: random_double!(rng, min, max),
         }
     }
 
-    pub(crate) fn length(&self) -> f64 {
+    pub fn length(&self) -> f64 {
         f64::sqrt(self.length_squared())
     }
 
-    pub(crate) fn length_squared(&self) -> f64 {
+    pub fn length_squared(&self) -> f64 {
         self[0].powi(2) + self[1].powi(2) + self[2].powi(2)
     }
 
-    pub(crate) fn near_zero(&self) -> bool {
+    pub fn near_zero(&self) -> bool {
         let s = 1e-8;
         (self.x.abs() < s) && (self.y.abs() < s) && (self.z.abs() < s)
     }
@@ -45,7 +42,7 @@ impl Vec3 {
 
 // Utility functions
 
-pub(crate) fn unit_vector(v: Vec3) -> Vec3 {
+pub fn unit_vector(v: Vec3) -> Vec3 {
     let length = v.length();
     Vec3::new(v[0] / length,
               v[1] / length,
@@ -57,9 +54,9 @@ pub(crate) fn unit_vector(v: Vec3) -> Vec3 {
 // Our criteria is outlined as follows:
 //     1. Pick a random point in a unit cube where x, y, z all range from -1 to 1.
 //     2. Reject a point if it outside the unit sphere.
-fn random_in_unit_sphere() -> Vec3 {
+fn random_in_unit_sphere(rng: &mut SmallRng) -> Vec3 {
     loop {
-        let p = Vec3::random(-1.0, 1.0);
+        let p = Vec3::random(rng, -1.0, 1.0);
         // formula of a sphere x^2 + y^2 + z^2 = r^2
         // since sphere is assumed to be unit length, we check if it's less than 1
         if p.length_squared() < 1.0 {
@@ -73,14 +70,25 @@ fn random_in_unit_sphere() -> Vec3 {
 //     1. Generate a random vector inside of the unit sphere
 //     2. Normalize this vector
 //     3. Invert the normalized vector if it falls onto the wrong hemisphere
-pub(crate) fn random_unit_vector() -> Vec3 {
+pub fn random_unit_vector(rng: &mut SmallRng) -> Vec3 {
     // normalizing vector makes x^2 + y^2 + z^2 = 1, a point on the surface of the sphere
-    unit_vector(random_in_unit_sphere())
+    unit_vector(random_in_unit_sphere(rng))
+}
+
+// Same rejection method as `random_in_unit_sphere`, but in the z = 0 plane. Used to pick ray
+// origins on a camera lens.
+pub fn random_in_unit_disk(rng: &mut SmallRng) -> Vec3 {
+    loop {
+        let p = Vec3::new(random_double!(rng, -1.0, 1.0), random_double!(rng, -1.0, 1.0), 0.0);
+        if p.length_squared() < 1.0 {
+            return p;
+        }
+    }
 }
 
 // To determine if vector is in the correct hemisphere, we can compare against the surface normal
-pub(crate) fn random_on_hemisphere(normal : Vec3) -> Vec3 {
-    let on_unit_sphere = random_unit_vector();
+pub fn random_on_hemisphere(rng: &mut SmallRng, normal : Vec3) -> Vec3 {
+    let on_unit_sphere = random_unit_vector(rng);
     if dot(on_unit_sphere, normal) > 0.0 { // In the same hemisphere as normal
         on_unit_sphere
     } else {
@@ -88,16 +96,44 @@ pub(crate) fn random_on_hemisphere(normal : Vec3) -> Vec3 {
     }
 }
 
-pub(crate) fn reflect(v : Vec3, n: Vec3) -> Vec3 {
+// https://raytracing.github.io/books/RayTracingTheRestOfYourLife.html#generatingrandomdirections/cosinesamplingahemisphere
+// A random direction on the hemisphere around +z, with a density proportional to cos(theta),
+// where theta is the angle from the z axis: p(direction) = cos(theta) / pi. That's the
+// distribution Lambertian surfaces scatter light in.
+pub fn random_cosine_direction(rng: &mut SmallRng) -> Vec3 {
+    let r1 = random_double!(rng);
+    let r2 = random_double!(rng);
+
+    let phi = 2.0 * PI * r1;
+    let x = f64::cos(phi) * f64::sqrt(r2);
+    let y = f64::sin(phi) * f64::sqrt(r2);
+    let z = f64::sqrt(1.0 - r2);
+
+    Vec3::new(x, y, z)
+}
+
+pub fn reflect(v : Vec3, n: Vec3) -> Vec3 {
     v - 2.0 * dot(v, n) * n
 }
 
-pub(crate) fn dot(lhs: Vec3, rhs: Vec3) -> f64 {
+// Snell's law, split into the parts of the refracted ray R' perpendicular and parallel to the
+// normal n:
+//     R'_perp     = eta / eta' * (R + cos(theta) * n)
+//     R'_parallel = -sqrt(1 - |R'_perp|^2) * n
+// where cos(theta) = -R . n, assuming both R and n are unit vectors.
+pub fn refract(uv: Vec3, n: Vec3, etai_over_etat: f64) -> Vec3 {
+    let cos_theta = f64::min(dot(-uv, n), 1.0);
+    let r_out_perp = etai_over_etat * (uv + cos_theta * n);
+    let r_out_parallel = -f64::sqrt(f64::abs(1.0 - r_out_perp.length_squared())) * n;
+    r_out_perp + r_out_parallel
+}
+
+pub fn dot(lhs: Vec3, rhs: Vec3) -> f64 {
     lhs[0] * rhs[0] + lhs[1] * rhs[1] + lhs[2] * rhs[2]
 }
 
-pub(crate) fn cross(lhs: Vec3, rhs: Vec3) -> Vec3 {
-    Vec3::new(lhs[1] * rhs[2] - lhs[2] - rhs[1],
+pub fn cross(lhs: Vec3, rhs: Vec3) -> Vec3 {
+    Vec3::new(lhs[1] * rhs[2] - lhs[2] * rhs[1],
               lhs[2] * rhs[0] - lhs[0] * rhs[2],
               lhs[0] * rhs[1] - lhs[1] * rhs[0])
 }
@@ -0,0 +1,110 @@
+//! Arbitrary output variables: what the camera rays first hit.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use engine::aabb::Aabb;
+use engine::bvh::BvhNode;
+use engine::integrator::{Albedo, Normals};
+use engine::interval::Interval;
+use engine::material::Lambertian;
+use engine::scene;
+use engine::sphere::Sphere;
+use engine::{Aov, Background, Camera, Color, HitRecord, Hittable, HittableList, Point3, Ray,
+             Scene, Vec3, AOVS};
+
+#[test]
+fn names() {
+    for aov in AOVS {
+        assert_eq!(Aov::from_name(aov.name()), Some(aov));
+    }
+    assert_eq!(Aov::from_name("motion"), None);
+    assert_eq!(Aov::from_name("Depth"), None);
+    assert_eq!(Aov::from_name("all"), None);
+}
+
+// The spheres of the final scene are all in one `BvhNode`, which is all there is in its world, and
+// each is still its own object. The node can also be rendered by itself.
+#[test]
+fn object_ids_inside_a_bvh() {
+    let Scene { world, mut camera, background, .. } =
+        scene::builtin("final", &mut engine::new_rng(Some(0))).unwrap();
+    camera.image_width = 64;
+    camera.samples_per_pixel = 4;
+    let (_, aov_buffers) = camera.render_with_aovs(world.objects[0].as_ref(), &background,
+                                                   &Normals, true, false);
+
+    let ids: HashSet<u32> = aov_buffers.object_id.iter().copied().collect();
+    assert!(ids.len() > 20, "only {} object IDs", ids.len());
+    // Numbered in the order they show up, so there are no gaps.
+    assert!(ids.iter().all(|&id| (id as usize) < ids.len() + 1));
+}
+
+// The AOVs come from the image's own camera rays, so with defocus and motion blur the albedo AOV
+// is still exactly what the albedo integrator draws, pixel for pixel. Diffuse spheres have the
+// same albedo whichever way they scatter, so the two only match if they see the same hits.
+#[test]
+fn aovs_match_the_image() {
+    let mut world = HittableList::new();
+    let red = Arc::new(Lambertian::new(Color::new(0.8, 0.1, 0.1)));
+    let blue = Arc::new(Lambertian::new(Color::new(0.1, 0.2, 0.8)));
+    world.add(Arc::new(Sphere::moving(Point3::new(-0.6, 0.0, 0.0), Point3::new(0.6, 0.3, 0.0),
+                                      0.5, Some(red))));
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, 0.0, -2.0), 1.0, Some(blue))));
+
+    let mut camera = Camera::new(32, 1.0, 16, 10);
+    camera.lookfrom = Point3::new(0.0, 0.0, 4.0);
+    camera.lookat = Point3::new(0.0, 0.0, 0.0);
+    camera.defocus_angle = 3.0;
+    camera.focus_dist = 6.0;
+    camera.shutter = Interval::new(0.0, 1.0);
+    camera.seed = Some(0);
+    let background = Background::Solid(Color::new(0.5, 0.7, 1.0));
+    let (framebuffer, aov_buffers) =
+        camera.render_with_aovs(&world, &background, &Albedo, true, false);
+    let albedo = aov_buffers.to_framebuffer(Aov::Albedo, false);
+
+    for j in 0..framebuffer.height() {
+        for i in 0..framebuffer.width() {
+            let difference = (framebuffer.pixel(i, j) - albedo.pixel(i, j)).length();
+            assert!(difference < 1e-9, "pixel {i}, {j} is off by {difference}");
+        }
+    }
+}
+
+// A square facing the camera at z = 0 that, like a `Hittable` written before object IDs, never sets
+// `object_id`.
+struct Wall;
+
+impl Hittable for Wall {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let t = -r.origin.z / r.direction.z;
+        let p = r.at(t);
+        if !ray_t.surrounds(t) || p.x.abs() > 1.0 || p.y.abs() > 1.0 {
+            return false;
+        }
+        rec.t = t;
+        rec.p = Some(p);
+        rec.set_face_normal(r, Vec3::new(0.0, 0.0, 1.0));
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::from_points(Point3::new(-1.0, -1.0, -0.001), Point3::new(1.0, 1.0, 0.001))
+    }
+}
+
+// Hitting the wall in front of a sphere, the wall's lack of an ID isn't taken to be the sphere's.
+#[test]
+fn objects_without_ids() {
+    let mut list = HittableList::new();
+    list.add(Arc::new(Sphere::new(Point3::new(0.0, 0.0, -5.0), 1.0, None)));
+    list.add(Arc::new(Wall));
+    let r = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+
+    for world in [&list as &dyn Hittable, &BvhNode::new(list.clone())] {
+        let mut rec = HitRecord::default();
+        assert!(world.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec));
+        assert_eq!(rec.t, 5.0);
+        assert_eq!(rec.object_id, 0);
+    }
+}
@@ -0,0 +1,14 @@
+//! Bounding volume hierarchies over unusual object lists.
+
+use engine::bvh::BvhNode;
+use engine::interval::Interval;
+use engine::{HitRecord, Hittable, HittableList, Point3, Ray, Vec3};
+
+#[test]
+fn empty_list() {
+    let bvh = BvhNode::new(HittableList::new());
+    for direction in [Vec3::new(0.0, 0.0, -1.0), Vec3::new(1.0, 2.0, 3.0)] {
+        let r = Ray::new(Point3::new(0.0, 0.0, 0.0), direction);
+        assert!(!bvh.hit(&r, Interval::new(0.001, f64::INFINITY), &mut HitRecord::default()));
+    }
+}
@@ -0,0 +1,73 @@
+//! Command lines the renderer should turn down, and outputs it can't write.
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_engine")).args(args).output()
+        .expect("Unable to run the renderer")
+}
+
+// Checks that `args` are rejected with a usage error mentioning `message`.
+fn check_rejected(args: &[&str], message: &str) {
+    let output = run(args);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(2), "{args:?} wasn't rejected: {stderr}");
+    assert!(stderr.contains(message), "{args:?}: expected `{message}` in: {stderr}");
+    assert!(stderr.contains("Usage:"), "{args:?}: no usage in: {stderr}");
+}
+
+#[test]
+fn help() {
+    let output = run(&["--help"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("--integrator <NAME>"));
+}
+
+#[test]
+fn unknown_integrator() {
+    check_rejected(&["--integrator", "photon_map"], "unknown integrator `photon_map`");
+    check_rejected(&["-i"], "missing value for `-i`");
+}
+
+#[test]
+fn unknown_aov() {
+    check_rejected(&["--aov", "depth,motion"], "unknown AOV `motion`");
+    check_rejected(&["--aov="], "unknown AOV ``");
+}
+
+#[test]
+fn numbers_must_be_positive() {
+    check_rejected(&["--width", "0"], "`--width` expects a positive integer, got `0`");
+    check_rejected(&["--samples=-4"], "`--samples` expects a positive integer, got `-4`");
+    check_rejected(&["-d", "deep"], "`-d` expects a positive integer, got `deep`");
+    check_rejected(&["--aspect-ratio", "16:0"], "invalid aspect ratio `16:0`");
+}
+
+#[test]
+fn bad_arguments() {
+    check_rejected(&["--fast"], "unknown option `--fast`");
+    check_rejected(&["final", "cornell_box"], "unexpected argument `cornell_box`");
+    check_rejected(&["--format", "jpg"], "unsupported format `jpg`");
+    check_rejected(&["-o", "out/final.jpg"], "can't tell the output format of `out/final.jpg`");
+}
+
+#[test]
+fn bad_scene_file() {
+    let output = run(&["does/not/exist.toml"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unable to read scene file"));
+}
+
+#[test]
+fn bad_output_path() {
+    // A directory can't be made inside a file.
+    let file = Path::new(env!("CARGO_TARGET_TMPDIR")).join("not_a_directory");
+    fs::write(&file, b"").unwrap();
+    let output_path = file.join("metal.png");
+    let output = run(&["metal", "-w", "8", "-s", "1", "-o", output_path.to_str().unwrap()]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1), "{stderr}");
+    assert!(stderr.contains("error: unable to write"), "{stderr}");
+}
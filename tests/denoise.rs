@@ -0,0 +1,60 @@
+//! Denoising images too small or too sharp for the firefly clamp to tell what's noise, or with
+//! pixels that aren't numbers at all.
+
+use engine::{Color, Framebuffer};
+
+fn luminance(c: Color) -> f64 {
+    0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z
+}
+
+// A pixel without any neighbours can't be compared with anything, so it comes out as it went in.
+#[test]
+fn single_pixel() {
+    let color = Color::new(0.2, 0.5, 0.8);
+    let denoised = engine::denoise(&Framebuffer::from_pixels(1, 1, vec![color]), None);
+    assert!((denoised.pixel(0, 0) - color).length() < 1e-12, "{:?}", denoised.pixel(0, 0));
+}
+
+// Two dim neighbours aren't enough to call a bright pixel a firefly.
+#[test]
+fn bright_pixel_between_two() {
+    let dim = Color::new(0.1, 0.1, 0.1);
+    let bright = Color::new(5.0, 5.0, 5.0);
+    let denoised = engine::denoise(&Framebuffer::from_pixels(3, 1, vec![dim, bright, dim]), None);
+    assert!(luminance(denoised.pixel(1, 0)) > 4.9, "{:?}", denoised.pixel(1, 0));
+    for i in [0, 2] {
+        assert!(luminance(denoised.pixel(i, 0)) < 0.2, "{:?}", denoised.pixel(i, 0));
+    }
+}
+
+// Among plenty of neighbours, the same pixel is a firefly and is dimmed to match them.
+#[test]
+fn firefly_among_many() {
+    let mut pixels = vec![Color::new(0.1, 0.1, 0.1); 9 * 9];
+    pixels[4 * 9 + 4] = Color::new(5.0, 5.0, 5.0);
+    let denoised = engine::denoise(&Framebuffer::from_pixels(9, 9, pixels), None);
+    for j in 0..9 {
+        for i in 0..9 {
+            assert!(luminance(denoised.pixel(i, j)) < 0.2, "pixel {i}, {j}");
+        }
+    }
+}
+
+// An infinite pixel is dropped, rather than turning its whole neighbourhood into NaN.
+#[test]
+fn infinite_pixel() {
+    let gray = Color::new(0.5, 0.5, 0.5);
+    let mut pixels = vec![gray; 9 * 9];
+    pixels[4 * 9 + 4] = Color::new(f64::INFINITY, 0.5, 0.5);
+    let denoised = engine::denoise(&Framebuffer::from_pixels(9, 9, pixels), None);
+    for j in 0..9 {
+        for i in 0..9 {
+            let pixel = denoised.pixel(i, j);
+            assert!(pixel.x.is_finite() && pixel.y.is_finite() && pixel.z.is_finite(),
+                    "pixel {i}, {j} is {pixel:?}");
+            if (i, j) != (4, 4) {
+                assert!((pixel - gray).length() < 0.01, "pixel {i}, {j} is {pixel:?}");
+            }
+        }
+    }
+}
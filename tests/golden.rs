@@ -0,0 +1,237 @@
+//! Golden-image regression tests.
+//!
+//! Each test renders a small scene with a fixed seed and compares the result with a reference
+//! image in `tests/golden/`. Rendering is deterministic for a given seed, but floating point math
+//! isn't guaranteed to match bit for bit across platforms and compilers, and one path taking a
+//! different bounce changes a whole pixel. So rather than demanding identical bytes, the images
+//! have to agree to within a peak signal-to-noise ratio.
+//!
+//! When a comparison fails, the render and an amplified difference image are written next to the
+//! test binaries under `target/tmp/golden/` to look at. After a change that is meant to alter the
+//! output, regenerate the references with `UPDATE_GOLDEN=1 cargo test --test golden`.
+
+use std::env;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use engine::integrator::{self, Normals, PathTracer};
+use engine::scene;
+use engine::{Color, Framebuffer, Scene, AOVS};
+
+const SEED: u64 = 2024;
+// Rendering the same scenes with a different seed only gets to 24-36 dB at these settings, so
+// this leaves room for the odd diverging path while still catching changes to the image.
+const MIN_PSNR: f64 = 40.0;
+// How much to scale up the per-component differences in the diff image so small ones show up.
+const DIFF_GAIN: f64 = 8.0;
+
+#[test]
+fn normals() {
+    let Scene { world, mut camera, background, .. } = builtin("metal");
+    let framebuffer = camera.render_framebuffer(&world, &background, &Normals, true, false);
+    check_golden("normals", &framebuffer);
+}
+
+#[test]
+fn metal() {
+    check_golden("metal", &render_builtin("metal"));
+}
+
+#[test]
+fn dielectric() {
+    check_golden("dielectric", &render_builtin("dielectric"));
+}
+
+#[test]
+fn defocus_blur() {
+    check_golden("defocus_blur", &render_builtin("defocus_blur"));
+}
+
+#[test]
+fn final_scene() {
+    check_golden("final", &render_builtin("final"));
+}
+
+#[test]
+fn bouncing_spheres() {
+    check_golden("bouncing_spheres", &render_builtin("bouncing_spheres"));
+}
+
+#[test]
+fn perlin_spheres() {
+    check_golden("perlin_spheres", &render_builtin("perlin_spheres"));
+}
+
+#[test]
+fn quads() {
+    check_golden("quads", &render_builtin("quads"));
+}
+
+#[test]
+fn cornell_box() {
+    check_golden("cornell_box", &render_builtin("cornell_box"));
+}
+
+#[test]
+fn cornell_smoke() {
+    check_golden("cornell_smoke", &render_builtin("cornell_smoke"));
+}
+
+#[test]
+fn albedo() {
+    check_golden("albedo", &render_builtin_with("cornell_box", "albedo"));
+}
+
+#[test]
+fn depth() {
+    check_golden("depth", &render_builtin_with("cornell_box", "depth"));
+}
+
+#[test]
+fn hit_count() {
+    check_golden("hit_count", &render_builtin_with("cornell_box", "hit_count"));
+}
+
+#[test]
+fn aovs() {
+    let Scene { world, mut camera, background, .. } = builtin("cornell_box");
+    let (_, aov_buffers) = camera.render_with_aovs(&world, &background, &Normals, true, false);
+    for aov in AOVS {
+        check_golden(&format!("aov_{}", aov.name()), &aov_buffers.to_framebuffer(aov, true));
+    }
+}
+
+#[test]
+fn denoise() {
+    let Scene { world, lights, mut camera, background } = builtin("cornell_box");
+    let (framebuffer, guides) = camera.render_with_aovs(&world, &background,
+                                                         &PathTracer::new(lights), true, true);
+    check_golden("denoise", &engine::denoise(&framebuffer, Some(&guides)));
+    check_golden("denoise_unguided", &engine::denoise(&framebuffer, None));
+}
+
+#[test]
+fn mesh() {
+    check_golden("mesh", &render_scene_file("mesh"));
+}
+
+#[test]
+fn planar() {
+    check_golden("planar", &render_scene_file("planar"));
+}
+
+#[test]
+fn instances() {
+    check_golden("instances", &render_scene_file("instances"));
+}
+
+#[test]
+fn lights() {
+    check_golden("lights", &render_scene_file("lights"));
+}
+
+#[test]
+fn environment() {
+    check_golden("environment", &render_scene_file("environment"));
+}
+
+#[test]
+fn textures() {
+    check_golden("textures", &render_scene_file("textures"));
+}
+
+fn builtin(name: &str) -> Scene {
+    shrink(scene::builtin(name, &mut engine::new_rng(Some(SEED))).unwrap())
+}
+
+// Shrinks a scene down to something that renders quickly in a debug build.
+fn shrink(mut scene: Scene) -> Scene {
+    scene.camera.image_width = 96;
+    scene.camera.samples_per_pixel = 16;
+    scene.camera.max_depth = 10;
+    scene.camera.seed = Some(SEED);
+    scene
+}
+
+fn render_builtin(name: &str) -> Framebuffer {
+    let Scene { world, lights, mut camera, background } = builtin(name);
+    camera.render_framebuffer(&world, &background, &PathTracer::new(lights), true, true)
+}
+
+// Renders a built-in scene with one of the other integrators, by name.
+fn render_builtin_with(name: &str, integrator: &str) -> Framebuffer {
+    let mut scene = builtin(name);
+    let integrator = integrator::from_name(integrator, &scene).unwrap();
+    scene.camera.render_framebuffer(&scene.world, &scene.background, integrator.as_ref(), true,
+                                    true)
+}
+
+// Renders `scenes/<name>.toml`.
+fn render_scene_file(name: &str) -> Framebuffer {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes").join(format!("{name}.toml"));
+    let Scene { world, lights, mut camera, background } =
+        shrink(scene::load(path.to_str().unwrap()).unwrap());
+    camera.render_framebuffer(&world, &background, &PathTracer::new(lights), true, true)
+}
+
+fn check_golden(name: &str, framebuffer: &Framebuffer) {
+    let golden_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+        .join(format!("{name}.png"));
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        framebuffer.save(golden_path.to_str().unwrap()).expect("Unable to write golden image");
+        return;
+    }
+
+    let Some((width, height, golden)) = read_png(&golden_path) else {
+        panic!("missing golden image {}, run with UPDATE_GOLDEN=1 to create it",
+               golden_path.display());
+    };
+    assert_eq!((width, height), (framebuffer.width(), framebuffer.height()),
+               "{name}: image size differs from the golden image");
+
+    let actual = framebuffer.to_rgb8();
+    let psnr = psnr(&actual, &golden);
+    if psnr < MIN_PSNR {
+        let render_path = failure_path(&format!("{name}.png"));
+        let diff_path = failure_path(&format!("{name}.diff.png"));
+        framebuffer.save(render_path.to_str().unwrap()).expect("Unable to write render");
+        diff_image(width, height, &actual, &golden)
+            .save(diff_path.to_str().unwrap()).expect("Unable to write diff image");
+        panic!("{name}: PSNR {psnr:.2} dB is below {MIN_PSNR} dB, see {} and {}",
+               render_path.display(), diff_path.display());
+    }
+}
+
+// Peak signal-to-noise ratio in decibels of two 8-bit images; infinite when they're identical.
+fn psnr(a: &[u8], b: &[u8]) -> f64 {
+    let squared_error: f64 = a.iter().zip(b)
+        .map(|(&a, &b)| (a as f64 - b as f64).powi(2))
+        .sum();
+    let mse = squared_error / a.len() as f64;
+    10.0 * f64::log10(255.0 * 255.0 / mse)
+}
+
+fn diff_image(width: usize, height: usize, a: &[u8], b: &[u8]) -> Framebuffer {
+    let pixels = a.chunks_exact(3).zip(b.chunks_exact(3))
+        .map(|(a, b)| {
+            let diff = |c: usize| DIFF_GAIN * (a[c] as f64 - b[c] as f64).abs() / 255.0;
+            Color::new(diff(0), diff(1), diff(2))
+        })
+        .collect();
+    Framebuffer::from_pixels(width, height, pixels)
+}
+
+fn failure_path(file_name: &str) -> PathBuf {
+    Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden").join(file_name)
+}
+
+// Reads an 8-bit RGB PNG as written by `Framebuffer::save`, or `None` if there's no such file.
+fn read_png(path: &Path) -> Option<(usize, usize, Vec<u8>)> {
+    let decoder = png::Decoder::new(File::open(path).ok()?);
+    let mut reader = decoder.read_info().expect("Unable to read golden image");
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).expect("Unable to decode golden image");
+    assert_eq!((info.color_type, info.bit_depth), (png::ColorType::Rgb, png::BitDepth::Eight),
+               "golden images must be 8-bit RGB");
+    buf.truncate(info.buffer_size());
+    Some((info.width as usize, info.height as usize, buf))
+}
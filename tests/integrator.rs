@@ -0,0 +1,52 @@
+//! Picking integrators by name, and following paths much deeper than any picture needs.
+
+use std::sync::Arc;
+use engine::integrator::{self, Diffuse, PathTracer, INTEGRATORS};
+use engine::material::Metal;
+use engine::scene;
+use engine::sphere::Sphere;
+use engine::{Background, Camera, Color, HittableList, Integrator, Point3};
+
+const MAX_DEPTH: i32 = 10_000;
+
+#[test]
+fn from_name() {
+    let scene = scene::builtin("cornell_box", &mut engine::new_rng(Some(0))).unwrap();
+    for name in INTEGRATORS {
+        assert!(integrator::from_name(name, &scene).is_some(), "{name}");
+    }
+    assert!(integrator::from_name("photon_map", &scene).is_none());
+    assert!(integrator::from_name("", &scene).is_none());
+}
+
+// Inside a mirrored sphere, a ray bounces until it runs out of `max_depth`. A box would do too,
+// but rays slip out through the cracks along its edges, while a mirror keeps a ray's angle to the
+// sphere, and so the distance between its bounces, the same all the way. Paths are followed in a
+// loop, so even this deep they can't overflow the stack.
+fn render_mirror_sphere(integrator: &dyn Integrator) {
+    let mut world = HittableList::new();
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, 0.0, 0.0), 10.0,
+                                   Some(Arc::new(Metal::new(Color::new(1.0, 1.0, 1.0), 0.0))))));
+    let mut camera = Camera::new(8, 1.0, 1, MAX_DEPTH);
+    camera.lookfrom = Point3::new(1.0, 2.0, 3.0);
+    camera.seed = Some(0);
+
+    let background = Background::Solid(Color::new(1.0, 1.0, 1.0));
+    let framebuffer = camera.render_framebuffer(&world, &background, integrator, true, false);
+    // No light gets into the sphere.
+    for j in 0..framebuffer.height() {
+        for i in 0..framebuffer.width() {
+            assert_eq!(framebuffer.pixel(i, j).length(), 0.0, "pixel {i}, {j}");
+        }
+    }
+}
+
+#[test]
+fn path_tracer_mirror_sphere() {
+    render_mirror_sphere(&PathTracer::new(HittableList::new()));
+}
+
+#[test]
+fn diffuse_mirror_sphere() {
+    render_mirror_sphere(&Diffuse);
+}
@@ -0,0 +1,115 @@
+//! Round trips through the image formats: what `Framebuffer::save` writes, `Framebuffer::load`
+//! has to read back.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use engine::output::Framebuffer;
+use engine::vec3::Color;
+
+#[test]
+fn ppm_round_trip() {
+    check_8_bit_round_trip("round_trip.ppm");
+}
+
+#[test]
+fn png_round_trip() {
+    check_8_bit_round_trip("round_trip.png");
+}
+
+#[test]
+fn plain_text_ppm() {
+    let path = temp_path("plain.ppm");
+    write(&path, b"P3\n# a comment\n2 1\n255\n255 0 0\n0 0 51\n");
+    let loaded = Framebuffer::load(path.to_str().unwrap()).unwrap();
+    assert_eq!((loaded.width(), loaded.height()), (2, 1));
+    assert_eq!((loaded.pixel(0, 0).x, loaded.pixel(0, 0).y), (1.0, 0.0));
+    assert_eq!(loaded.pixel(1, 0).z, 0.2 * 0.2);
+}
+
+#[test]
+fn images_without_pixels_are_rejected() {
+    for (file_name, contents) in [("empty.ppm", &b"P3 0 4 255\n"[..]),
+                                  ("empty_binary.ppm", b"P6 4 0 255\n"),
+                                  ("empty.hdr", b"#?RADIANCE\n\n-Y 0 +X 4\n"),
+                                  ("empty_scanline.hdr", b"#?RADIANCE\n\n-Y 1 +X 0\n")] {
+        let path = temp_path(file_name);
+        write(&path, contents);
+        let err = Framebuffer::load(path.to_str().unwrap()).err().expect(file_name);
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{file_name}: {err}");
+    }
+}
+
+#[test]
+fn oversized_headers_are_rejected() {
+    let huge = usize::MAX / 2;
+    for (file_name, contents) in [("huge.ppm", format!("P6 {huge} 2 255\n")),
+                                  ("huge.hdr", format!("#?RADIANCE\n\n-Y 2 +X {huge}\n")),
+                                  // Would need 4 TB for a single scanline.
+                                  ("wide.hdr", "#?RADIANCE\n\n-Y 1 +X 1000000000000\n".into())] {
+        let path = temp_path(file_name);
+        write(&path, contents.as_bytes());
+        let err = Framebuffer::load(path.to_str().unwrap()).err().expect(file_name);
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{file_name}: {err}");
+    }
+}
+
+#[test]
+fn hdr_keeps_out_of_range_pixels() {
+    let pixels = vec![Color::new(0.25, 1.0, 4.0), Color::new(f64::INFINITY, 0.0, 0.0),
+                      Color::new(1e300, 1e300, 1e300), Color::new(0.0, 0.0, 0.0)];
+    let loaded = round_trip("out_of_range.hdr", &Framebuffer::from_pixels(2, 2, pixels));
+
+    // The channels share an exponent, so they're stored in steps of 1/256 of the power of two
+    // above the brightest one, 8 here.
+    let error = loaded.pixel(0, 0) - Color::new(0.25, 1.0, 4.0);
+    assert!([error.x, error.y, error.z].iter().all(|e| e.abs() <= 8.0 / 256.0), "{error:?}");
+    // Anything brighter than RGBE can store comes back as the brightest value there is, just
+    // under 2^127.
+    let brightest = 255.5 / 256.0 * 2f64.powi(127);
+    assert_eq!(loaded.pixel(1, 0).x, brightest);
+    assert_eq!(loaded.pixel(0, 1).x, brightest);
+    assert_eq!(loaded.pixel(1, 1).length(), 0.0);
+}
+
+// 8-bit images hold gamma encoded values, so a round trip gives back the linear colors to within
+// one step of 1/255 after gamma encoding.
+fn check_8_bit_round_trip(file_name: &str) {
+    let (width, height) = (5, 3);
+    let pixels = (0..width * height)
+        .map(|index| {
+            let c = index as f64 / (width * height - 1) as f64;
+            Color::new(c, 1.0 - c, c * c)
+        })
+        .collect();
+    let mut framebuffer = Framebuffer::from_pixels(width, height, pixels);
+    framebuffer.gamma_correction = true;
+    let loaded = round_trip(file_name, &framebuffer);
+
+    assert_eq!((loaded.width(), loaded.height()), (width, height));
+    for j in 0..height {
+        for i in 0..width {
+            let (expected, actual) = (framebuffer.pixel(i, j), loaded.pixel(i, j));
+            for (e, a) in [(expected.x, actual.x), (expected.y, actual.y), (expected.z, actual.z)] {
+                assert!((e.sqrt() - a.sqrt()).abs() <= 1.0 / 255.0,
+                        "{file_name}: pixel {i}, {j} is {actual:?}, expected {expected:?}");
+            }
+        }
+    }
+}
+
+// Saves `framebuffer` as `file_name` and loads it back.
+fn round_trip(file_name: &str, framebuffer: &Framebuffer) -> Framebuffer {
+    let path = temp_path(file_name);
+    framebuffer.save(path.to_str().unwrap()).expect("Unable to write image");
+    Framebuffer::load(path.to_str().unwrap()).expect("Unable to read image")
+}
+
+fn temp_path(file_name: &str) -> PathBuf {
+    Path::new(env!("CARGO_TARGET_TMPDIR")).join("output").join(file_name)
+}
+
+fn write(path: &Path, contents: &[u8]) {
+    fs::create_dir_all(path.parent().unwrap()).expect("Unable to create directory");
+    fs::write(path, contents).expect("Unable to write file");
+}
@@ -0,0 +1,125 @@
+//! Scene files that shouldn't load, and the errors they get.
+
+use std::path::Path;
+use engine::scene::{self, SceneError};
+
+// The smallest scene file there is, with the given camera settings.
+fn scene_file(image_width: i32, samples_per_pixel: i32, max_depth: i32) -> String {
+    format!("[camera]\nimage_width = {image_width}\naspect_ratio = 1.0\n\
+             samples_per_pixel = {samples_per_pixel}\nmax_depth = {max_depth}\n")
+}
+
+#[test]
+fn minimal_scene_loads() {
+    let scene = scene::parse(&scene_file(4, 1, 1)).unwrap();
+    assert!(scene.world.is_empty());
+}
+
+#[test]
+fn invalid_toml() {
+    // Broken TOML, a setting of the wrong type, no camera, and a setting that doesn't exist.
+    for contents in ["[camera".to_string(), "[camera]\nimage_width = \"wide\"".to_string(),
+                     String::new(), format!("{}fov = 90\n", scene_file(4, 1, 1))] {
+        match scene::parse(&contents) {
+            Err(SceneError::Parse(_)) => {}
+            Err(err) => panic!("{contents:?}: unexpected error: {err}"),
+            Ok(_) => panic!("{contents:?} was accepted"),
+        }
+    }
+}
+
+#[test]
+fn missing_file() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes/missing.toml");
+    assert!(matches!(scene::load(path.to_str().unwrap()), Err(SceneError::Io(_))));
+}
+
+#[test]
+fn unknown_material() {
+    let contents = format!("{}[[objects]]\ntype = \"sphere\"\ncenter = [0, 0, 0]\nradius = 1\n\
+                            material = \"chrome\"\n", scene_file(4, 1, 1));
+    match scene::parse(&contents) {
+        Err(SceneError::UnknownMaterial { object: 0, name }) => assert_eq!(name, "chrome"),
+        Err(err) => panic!("unexpected error: {err}"),
+        Ok(_) => panic!("an unknown material was accepted"),
+    }
+}
+
+#[test]
+fn flattening_transform() {
+    let contents = format!("{}[[objects]]\ntype = \"sphere\"\ncenter = [0, 0, 0]\nradius = 1\n\
+                            transform = {{ scale = [1, 0, 1] }}\n", scene_file(4, 1, 1));
+    assert!(matches!(scene::parse(&contents), Err(SceneError::InvalidTransform { object: 0 })));
+}
+
+#[test]
+fn camera_settings_must_be_positive() {
+    let mut cases = Vec::new();
+    for value in [0, -5] {
+        cases.push(("image_width", scene_file(value, 1, 1)));
+        cases.push(("samples_per_pixel", scene_file(4, value, 1)));
+        cases.push(("max_depth", scene_file(4, 1, value)));
+    }
+    for value in ["0.0", "-1.5", "nan", "inf"] {
+        let with_aspect_ratio = scene_file(4, 1, 1)
+            .replace("aspect_ratio = 1.0", &format!("aspect_ratio = {value}"));
+        cases.push(("aspect_ratio", with_aspect_ratio));
+        cases.push(("focus_dist", format!("{}focus_dist = {value}\n", scene_file(4, 1, 1))));
+    }
+    for value in ["0.0", "180.0", "-30.0", "nan"] {
+        cases.push(("vfov", format!("{}vfov = {value}\n", scene_file(4, 1, 1))));
+    }
+    cases.push(("lookat", format!("{}lookfrom = [1, 2, 3]\nlookat = [1, 2, 3]\n",
+                                  scene_file(4, 1, 1))));
+    cases.push(("vup", format!("{}lookfrom = [0, 5, 0]\nlookat = [0, 0, 0]\n",
+                               scene_file(4, 1, 1))));
+    cases.push(("vup", format!("{}vup = [0, 0, 0]\n", scene_file(4, 1, 1))));
+
+    for (setting, contents) in cases {
+        match scene::parse(&contents) {
+            Err(SceneError::InvalidCamera { setting: invalid }) => assert_eq!(invalid, setting),
+            Err(err) => panic!("{contents:?}: unexpected error: {err}"),
+            Ok(_) => panic!("{contents:?} was accepted"),
+        }
+    }
+}
+
+// A scene file with one object made of fog, `shape` being the rest of the object's settings.
+fn volume_scene_file(shape: &str, density: &str) -> String {
+    format!("{}\n[materials.fog]\ntype = \"isotropic\"\nalbedo = [1, 1, 1]\n\n\
+             [[objects]]\n{shape}\nmaterial = \"fog\"\ndensity = {density}\n",
+            scene_file(4, 1, 1))
+}
+
+const SPHERE: &str = "type = \"sphere\"\ncenter = [0, 0, 0]\nradius = 1";
+
+#[test]
+fn volumes_load() {
+    let scene = scene::parse(&volume_scene_file(SPHERE, "0.5")).unwrap();
+    assert!(!scene.world.is_empty());
+    assert!(scene.lights.is_empty());
+}
+
+#[test]
+fn volume_density_must_be_positive() {
+    for density in ["0.0", "-0.5", "nan"] {
+        match scene::parse(&volume_scene_file(SPHERE, density)) {
+            Err(SceneError::InvalidDensity { object: 0 }) => {}
+            Err(err) => panic!("density = {density}: unexpected error: {err}"),
+            Ok(_) => panic!("density = {density} was accepted"),
+        }
+    }
+}
+
+#[test]
+fn open_shapes_cant_be_volumes() {
+    for shape in ["type = \"plane\"\npoint = [0, 0, 0]\nnormal = [0, 1, 0]",
+                  "type = \"quad\"\nq = [0, 0, 0]\nu = [1, 0, 0]\nv = [0, 1, 0]",
+                  "type = \"disk\"\ncenter = [0, 0, 0]\nnormal = [0, 1, 0]\nradius = 1"] {
+        match scene::parse(&volume_scene_file(shape, "0.5")) {
+            Err(SceneError::OpenVolume { object: 0 }) => {}
+            Err(err) => panic!("{shape}: unexpected error: {err}"),
+            Ok(_) => panic!("{shape} was accepted as a volume"),
+        }
+    }
+}
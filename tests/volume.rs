@@ -0,0 +1,52 @@
+//! How much light makes it through volumes of `ConstantMedium`.
+
+use std::sync::Arc;
+use engine::constant_medium::ConstantMedium;
+use engine::interval::Interval;
+use engine::material::Isotropic;
+use engine::sphere::Sphere;
+use engine::{Color, HitRecord, Hittable, HittableList, Point3, Ray, Vec3};
+
+const DENSITY: f64 = 0.5;
+const RAYS: usize = 20_000;
+
+// A sphere of fog with a radius of 1 around `center`.
+fn fog_sphere(center: Point3) -> Arc<dyn Hittable> {
+    let boundary = Arc::new(Sphere::new(center, 1.0, None));
+    Arc::new(ConstantMedium::new(boundary, DENSITY,
+                                 Some(Arc::new(Isotropic::new(Color::new(1.0, 1.0, 1.0))))))
+}
+
+// The fraction of rays along the x axis that get through `world` without scattering. The rays
+// are sent at different times so each is scattered differently.
+fn transmittance(world: &dyn Hittable) -> f64 {
+    let passed = (0..RAYS)
+        .filter(|&i| {
+            let r = Ray::with_time(Point3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0),
+                                   i as f64 / RAYS as f64);
+            let mut rec = HitRecord::default();
+            !world.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec)
+        })
+        .count();
+    passed as f64 / RAYS as f64
+}
+
+// Beer-Lambert: a ray gets through a distance d of fog without scattering with a probability of
+// e^(-density d), and through two volumes if it gets through each of them.
+#[test]
+fn transmittance_through_two_volumes() {
+    // About 4 standard deviations of the estimates.
+    let tolerance = 0.015;
+
+    let one = fog_sphere(Point3::new(0.0, 0.0, 0.0));
+    let expected = f64::exp(-DENSITY * 2.0);
+    let actual = transmittance(one.as_ref());
+    assert!((actual - expected).abs() < tolerance, "one volume: {actual}, expected {expected}");
+
+    let mut two = HittableList::new();
+    two.add(one);
+    two.add(fog_sphere(Point3::new(4.0, 0.0, 0.0)));
+    let expected = f64::exp(-DENSITY * 4.0);
+    let actual = transmittance(&two);
+    assert!((actual - expected).abs() < tolerance, "two volumes: {actual}, expected {expected}");
+}